@@ -0,0 +1,535 @@
+use crate::Rect;
+
+const MIN_RATIO: f32 = 0.1;
+const MAX_RATIO: f32 = 0.9;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+impl Orientation {
+    fn flip(self) -> Self {
+        match self {
+            Orientation::Horizontal => Orientation::Vertical,
+            Orientation::Vertical => Orientation::Horizontal,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node<T> {
+    Leaf(T),
+    Split {
+        orientation: Orientation,
+        ratio: f32,
+        first: Box<Node<T>>,
+        second: Box<Node<T>>,
+    },
+}
+
+impl<T: Copy + PartialEq> Node<T> {
+    fn contains(&self, key: T) -> bool {
+        match self {
+            Node::Leaf(k) => *k == key,
+            Node::Split { first, second, .. } => first.contains(key) || second.contains(key),
+        }
+    }
+
+    fn keys(&self, out: &mut Vec<T>) {
+        match self {
+            Node::Leaf(k) => out.push(*k),
+            Node::Split { first, second, .. } => {
+                first.keys(out);
+                second.keys(out);
+            }
+        }
+    }
+
+    fn leaves(&self) -> usize {
+        match self {
+            Node::Leaf(_) => 1,
+            Node::Split { first, second, .. } => first.leaves() + second.leaves(),
+        }
+    }
+
+    /// Pixels the subtree needs along `orientation` to give every window
+    /// at least one.
+    fn extent(&self, orientation: Orientation) -> i32 {
+        match self {
+            Node::Leaf(_) => 1,
+            Node::Split {
+                orientation: own,
+                first,
+                second,
+                ..
+            } => {
+                let (a, b) = (first.extent(orientation), second.extent(orientation));
+                match *own == orientation {
+                    true => a + b,
+                    false => a.max(b),
+                }
+            }
+        }
+    }
+
+    fn last_key(&self) -> T {
+        match self {
+            Node::Leaf(k) => *k,
+            Node::Split { second, .. } => second.last_key(),
+        }
+    }
+
+    fn insert(&mut self, key: T, at: T, depth: usize) -> bool {
+        match self {
+            Node::Leaf(k) if *k == at => {
+                let orientation = match depth % 2 {
+                    0 => Orientation::Horizontal,
+                    _ => Orientation::Vertical,
+                };
+                *self = Node::Split {
+                    orientation,
+                    ratio: 0.5,
+                    first: Box::new(Node::Leaf(at)),
+                    second: Box::new(Node::Leaf(key)),
+                };
+                true
+            }
+            Node::Leaf(_) => false,
+            Node::Split { first, second, .. } => {
+                first.insert(key, at, depth + 1) || second.insert(key, at, depth + 1)
+            }
+        }
+    }
+
+    fn remove(self, key: T) -> (Option<Node<T>>, bool) {
+        match self {
+            Node::Leaf(k) if k == key => (None, true),
+            Node::Leaf(_) => (Some(self), false),
+            Node::Split {
+                orientation,
+                ratio,
+                first,
+                second,
+            } => {
+                let (first, removed) = first.remove(key);
+                let (second, removed) = if removed {
+                    (Some(*second), true)
+                } else {
+                    second.remove(key)
+                };
+                let node = match (first, second) {
+                    (Some(first), Some(second)) => Some(Node::Split {
+                        orientation,
+                        ratio,
+                        first: Box::new(first),
+                        second: Box::new(second),
+                    }),
+                    (Some(only), None) | (None, Some(only)) => Some(only),
+                    (None, None) => None,
+                };
+                (node, removed)
+            }
+        }
+    }
+
+    fn parent_of(&mut self, key: T) -> Option<&mut Node<T>> {
+        let is_child = match self {
+            Node::Split { first, second, .. } => {
+                matches!(**first, Node::Leaf(k) if k == key)
+                    || matches!(**second, Node::Leaf(k) if k == key)
+            }
+            Node::Leaf(_) => return None,
+        };
+        if is_child {
+            return Some(self);
+        }
+        match self {
+            Node::Split { first, .. } if first.contains(key) => first.parent_of(key),
+            Node::Split { second, .. } => second.parent_of(key),
+            Node::Leaf(_) => None,
+        }
+    }
+
+    fn rotate(&mut self) {
+        if let Node::Split {
+            orientation,
+            first,
+            second,
+            ratio,
+        } = self
+        {
+            if *orientation == Orientation::Vertical {
+                std::mem::swap(first, second);
+                *ratio = 1.0 - *ratio;
+            }
+            *orientation = orientation.flip();
+            first.rotate();
+            second.rotate();
+        }
+    }
+
+    fn balance(&mut self) {
+        if let Node::Split {
+            ratio,
+            first,
+            second,
+            ..
+        } = self
+        {
+            *ratio = first.leaves() as f32 / (first.leaves() + second.leaves()) as f32;
+            first.balance();
+            second.balance();
+        }
+    }
+
+    fn swap(&mut self, a: T, b: T) {
+        match self {
+            Node::Leaf(k) if *k == a => *k = b,
+            Node::Leaf(k) if *k == b => *k = a,
+            Node::Leaf(_) => {}
+            Node::Split { first, second, .. } => {
+                first.swap(a, b);
+                second.swap(a, b);
+            }
+        }
+    }
+
+    fn rects(&self, bounds: Rect, out: &mut Vec<(T, Rect)>) {
+        match self {
+            Node::Leaf(k) => out.push((*k, bounds)),
+            Node::Split {
+                orientation,
+                ratio,
+                first,
+                second,
+            } => {
+                let reserve = (first.extent(*orientation), second.extent(*orientation));
+                let (a, b) = match orientation {
+                    Orientation::Horizontal => {
+                        let split = split_point(bounds.left, bounds.right, *ratio, reserve);
+                        (
+                            Rect {
+                                right: split,
+                                ..bounds
+                            },
+                            Rect {
+                                left: split,
+                                ..bounds
+                            },
+                        )
+                    }
+                    Orientation::Vertical => {
+                        let split = split_point(bounds.top, bounds.bottom, *ratio, reserve);
+                        (
+                            Rect {
+                                bottom: split,
+                                ..bounds
+                            },
+                            Rect {
+                                top: split,
+                                ..bounds
+                            },
+                        )
+                    }
+                };
+                first.rects(a, out);
+                second.rects(b, out);
+            }
+        }
+    }
+}
+
+/// Splits `start..end` at `ratio`, leaving each side the pixels it needs
+/// when there are enough to go around.
+fn split_point(start: i32, end: i32, ratio: f32, (first, second): (i32, i32)) -> i32 {
+    let point = start + ((end - start) as f32 * ratio).round() as i32;
+    if end - start < first + second {
+        return point.clamp(start, end);
+    }
+    point.clamp(start + first, end - second)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BspTree<T> {
+    root: Option<Node<T>>,
+}
+
+impl<T> Default for BspTree<T> {
+    fn default() -> Self {
+        BspTree { root: None }
+    }
+}
+
+impl<T: Copy + PartialEq> BspTree<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.root.as_ref().map_or(0, Node::leaves)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    pub fn contains(&self, key: T) -> bool {
+        self.root.as_ref().is_some_and(|root| root.contains(key))
+    }
+
+    pub fn keys(&self) -> Vec<T> {
+        let mut out = vec![];
+        if let Some(root) = &self.root {
+            root.keys(&mut out);
+        }
+        out
+    }
+
+    pub fn insert(&mut self, key: T, at: Option<T>) {
+        if self.contains(key) {
+            return;
+        }
+        match &mut self.root {
+            None => self.root = Some(Node::Leaf(key)),
+            Some(root) => {
+                let at = at
+                    .filter(|&at| root.contains(at))
+                    .unwrap_or_else(|| root.last_key());
+                root.insert(key, at, 0);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: T) -> bool {
+        let Some(root) = self.root.take() else {
+            return false;
+        };
+        let (root, removed) = root.remove(key);
+        self.root = root;
+        removed
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(T) -> bool) {
+        for key in self.keys() {
+            if !f(key) {
+                self.remove(key);
+            }
+        }
+    }
+
+    pub fn rotate(&mut self, key: T) {
+        if let Some(parent) = self.root.as_mut().and_then(|root| root.parent_of(key)) {
+            parent.rotate();
+        }
+    }
+
+    pub fn balance(&mut self) {
+        if let Some(root) = &mut self.root {
+            root.balance();
+        }
+    }
+
+    pub fn adjust_ratio(&mut self, key: T, delta: f32) {
+        if let Some(Node::Split { ratio, first, .. }) =
+            self.root.as_mut().and_then(|root| root.parent_of(key))
+        {
+            let delta = if first.contains(key) { delta } else { -delta };
+            *ratio = (*ratio + delta).clamp(MIN_RATIO, MAX_RATIO);
+        }
+    }
+
+    pub fn swap(&mut self, a: T, b: T) {
+        if a != b && self.contains(a) && self.contains(b) {
+            if let Some(root) = &mut self.root {
+                root.swap(a, b);
+            }
+        }
+    }
+
+    pub fn rects(&self, bounds: Rect) -> Vec<(T, Rect)> {
+        let mut out = vec![];
+        if let Some(root) = &self.root {
+            root.rects(bounds, &mut out);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn tree(keys: &[u32]) -> BspTree<u32> {
+        let mut tree = BspTree::new();
+        for &key in keys {
+            tree.insert(key, None);
+        }
+        tree
+    }
+
+    fn area(r: &Rect) -> i64 {
+        (r.right - r.left) as i64 * (r.bottom - r.top) as i64
+    }
+
+    const BOUNDS: Rect = Rect {
+        left: 0,
+        top: 0,
+        right: 1000,
+        bottom: 800,
+    };
+
+    #[test]
+    fn empty_tree_has_no_rects() {
+        let tree: BspTree<u32> = BspTree::new();
+        assert!(tree.is_empty());
+        assert!(tree.rects(BOUNDS).is_empty());
+    }
+
+    #[test]
+    fn single_window_fills_bounds() {
+        assert_eq!(tree(&[1]).rects(BOUNDS), vec![(1, BOUNDS)]);
+    }
+
+    #[test]
+    fn insert_without_focus_splits_last_leaf() {
+        assert_eq!(
+            tree(&[1, 2, 3]).rects(BOUNDS),
+            vec![
+                (1, rect(0, 0, 500, 800)),
+                (2, rect(500, 0, 1000, 400)),
+                (3, rect(500, 400, 1000, 800)),
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_splits_focused_leaf() {
+        let mut tree = tree(&[1, 2]);
+        tree.insert(3, Some(1));
+        assert_eq!(
+            tree.rects(BOUNDS),
+            vec![
+                (1, rect(0, 0, 500, 400)),
+                (3, rect(0, 400, 500, 800)),
+                (2, rect(500, 0, 1000, 800)),
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_ignores_duplicates_and_unknown_focus() {
+        let mut tree = tree(&[1, 2]);
+        tree.insert(2, Some(1));
+        tree.insert(3, Some(42));
+        assert_eq!(tree.keys(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn remove_collapses_parent() {
+        let mut tree = tree(&[1, 2, 3]);
+        assert!(tree.remove(2));
+        assert_eq!(
+            tree.rects(BOUNDS),
+            vec![(1, rect(0, 0, 500, 800)), (3, rect(500, 0, 1000, 800))]
+        );
+        assert!(!tree.remove(2));
+        assert!(tree.remove(1));
+        assert!(tree.remove(3));
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn retain_keeps_structure_of_remaining_windows() {
+        let mut tree = tree(&[1, 2, 3, 4]);
+        tree.retain(|k| k % 2 == 0);
+        assert_eq!(
+            tree.rects(BOUNDS),
+            vec![(2, rect(0, 0, 1000, 400)), (4, rect(0, 400, 1000, 800))]
+        );
+    }
+
+    #[test]
+    fn rotate_turns_parent_subtree_clockwise() {
+        let mut tree = tree(&[1, 2]);
+        tree.rotate(1);
+        assert_eq!(
+            tree.rects(BOUNDS),
+            vec![(1, rect(0, 0, 1000, 400)), (2, rect(0, 400, 1000, 800))]
+        );
+        tree.rotate(2);
+        assert_eq!(
+            tree.rects(BOUNDS),
+            vec![(2, rect(0, 0, 500, 800)), (1, rect(500, 0, 1000, 800))]
+        );
+    }
+
+    #[test]
+    fn balance_gives_every_window_the_same_area() {
+        let mut tree = tree(&[1, 2, 3, 4]);
+        tree.balance();
+        let rects = tree.rects(rect(0, 0, 1200, 1200));
+        assert!(rects.iter().all(|(_, r)| area(r) == 1200 * 1200 / 4));
+    }
+
+    #[test]
+    fn adjust_ratio_grows_the_window() {
+        let mut tree = tree(&[1, 2]);
+        tree.adjust_ratio(2, 0.1);
+        assert_eq!(
+            tree.rects(BOUNDS),
+            vec![(1, rect(0, 0, 400, 800)), (2, rect(400, 0, 1000, 800))]
+        );
+        tree.adjust_ratio(1, 1.0);
+        assert_eq!(tree.rects(BOUNDS)[0].1, rect(0, 0, 900, 800));
+    }
+
+    #[test]
+    fn swap_exchanges_positions() {
+        let mut tree = tree(&[1, 2, 3]);
+        tree.swap(1, 3);
+        assert_eq!(tree.keys(), vec![3, 2, 1]);
+        tree.swap(1, 42);
+        assert_eq!(tree.keys(), vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn deep_tree_gives_every_window_a_pixel() {
+        let tree = tree(&(0..12).collect::<Vec<_>>());
+        let rects = tree.rects(rect(0, 0, 8, 8));
+        assert_eq!(rects.len(), 12);
+        assert!(rects
+            .iter()
+            .all(|(_, r)| r.right > r.left && r.bottom > r.top));
+        assert_eq!(rects.iter().map(|(_, r)| area(r)).sum::<i64>(), 64);
+    }
+
+    #[test]
+    fn rects_tile_bounds() {
+        let bounds = rect(-1920, 17, 1, 1057);
+        let mut tree = tree(&[1, 2, 3, 4, 5, 6, 7]);
+        tree.insert(8, Some(2));
+        tree.rotate(4);
+        tree.remove(5);
+        let rects: Vec<Rect> = tree.rects(bounds).into_iter().map(|(_, r)| r).collect();
+        assert_eq!(rects.len(), tree.len());
+        assert_eq!(rects.iter().map(area).sum::<i64>(), area(&bounds));
+        for (i, a) in rects.iter().enumerate() {
+            for b in rects.iter().skip(i + 1) {
+                let overlaps =
+                    a.left < b.right && b.left < a.right && a.top < b.bottom && b.top < a.bottom;
+                assert!(!overlaps, "{a:?} overlaps {b:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,682 @@
+use crate::{
+    layout::{CustomLayout, Direction, LayoutRegistry, Node, Transform, MAX_RATIO, MIN_RATIO},
+    minsize::MinSize,
+    Rect, Result,
+};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    fs::{copy, create_dir, File},
+    path::Path,
+};
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Config {
+    #[serde(rename = "Windows.UI.Core.CoreWindow")]
+    pub windows_ui_core_corewindow: Option<Vec<String>>,
+    pub class_names: Option<Vec<String>>,
+    pub process_names: Option<Vec<String>>,
+    pub titles: Option<Vec<String>>,
+    #[serde(rename = "layout")]
+    pub default_layout: Option<String>,
+    pub master_count: Option<usize>,
+    pub master_ratio: Option<f32>,
+    pub stack_balance: Option<f32>,
+    pub column_width: Option<f32>,
+    pub gaps: Option<Gaps>,
+    pub layout_gaps: Option<HashMap<String, Gaps>>,
+    pub layout_transforms: Option<HashMap<String, Transforms>>,
+    /// Sorted so layouts are registered in the same order every run.
+    pub custom_layouts: Option<BTreeMap<String, LayoutNode>>,
+    pub min_sizes: Option<Vec<MinSizeRule>>,
+    pub auto_layout: Option<Vec<AutoLayoutRule>>,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutNode {
+    pub split: Option<String>,
+    pub weight: Option<f32>,
+    pub children: Option<Vec<LayoutNode>>,
+    pub slots: Option<usize>,
+    pub stack: Option<String>,
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+pub struct Gaps {
+    pub inner: Option<i32>,
+    pub outer: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+impl Orientation {
+    pub fn of(area: Rect) -> Self {
+        match area.bottom - area.top > area.right - area.left {
+            true => Orientation::Portrait,
+            false => Orientation::Landscape,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct AutoLayoutRule {
+    pub min_windows: Option<usize>,
+    pub max_windows: Option<usize>,
+    pub orientation: Option<Orientation>,
+    pub layout: String,
+}
+
+impl AutoLayoutRule {
+    fn matches(&self, windows: usize, orientation: Orientation) -> bool {
+        self.min_windows.is_none_or(|min| windows >= min)
+            && self.max_windows.is_none_or(|max| windows <= max)
+            && self.orientation.is_none_or(|o| o == orientation)
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct MinSizeRule {
+    pub class_name: Option<String>,
+    pub process_name: Option<String>,
+    pub title: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+impl MinSizeRule {
+    fn matches(&self, class_name: &str, process_name: &str, title: &str) -> bool {
+        let is_match = |pattern: &Option<String>, value: &str| {
+            pattern.as_ref().is_none_or(|p| value.contains(p.as_str()))
+        };
+        is_match(&self.class_name, class_name)
+            && is_match(&self.process_name, process_name)
+            && is_match(&self.title, title)
+    }
+}
+
+#[derive(Clone, Copy, Default, Deserialize, Serialize)]
+pub struct Transforms {
+    pub mirror: Option<bool>,
+    pub flip: Option<bool>,
+    pub transpose: Option<bool>,
+}
+
+impl std::ops::Add for Gaps {
+    type Output = Gaps;
+    fn add(self, other: Gaps) -> Gaps {
+        Gaps {
+            inner: merge_option(self.inner, other.inner),
+            outer: merge_option(self.outer, other.outer),
+        }
+    }
+}
+
+impl std::ops::Add for Config {
+    type Output = Config;
+    fn add(self, other: Config) -> Config {
+        Config {
+            windows_ui_core_corewindow: self.windows_ui_core_corewindow,
+            class_names: merge_option_vecs(self.class_names, other.class_names),
+            process_names: merge_option_vecs(self.process_names, other.process_names),
+            titles: merge_option_vecs(self.titles, other.titles),
+            default_layout: merge_option(self.default_layout, other.default_layout),
+            master_count: merge_option(self.master_count, other.master_count),
+            master_ratio: merge_option(self.master_ratio, other.master_ratio),
+            stack_balance: merge_option(self.stack_balance, other.stack_balance),
+            column_width: merge_option(self.column_width, other.column_width),
+            gaps: match (self.gaps, other.gaps) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            },
+            layout_gaps: merge_option_maps(self.layout_gaps, other.layout_gaps),
+            layout_transforms: merge_option_maps(self.layout_transforms, other.layout_transforms),
+            custom_layouts: merge_option_maps(self.custom_layouts, other.custom_layouts),
+            min_sizes: merge_option_vecs(self.min_sizes, other.min_sizes),
+            auto_layout: merge_option(self.auto_layout, other.auto_layout),
+        }
+    }
+}
+
+impl Config {
+    /// Catches values serde takes but the layouts can't work with.
+    pub fn validate(&self) -> Result<()> {
+        let ratios = [
+            ("master_ratio", self.master_ratio, MIN_RATIO..=MAX_RATIO),
+            ("stack_balance", self.stack_balance, 0.0..=1.0),
+        ];
+        for (name, value, range) in ratios {
+            if let Some(value) = value.filter(|value| !range.contains(value)) {
+                return Err(format!(
+                    "{name} is {value}, it must be between {} and {}",
+                    range.start(),
+                    range.end()
+                )
+                .into());
+            }
+        }
+        let layout_gaps = self
+            .layout_gaps
+            .iter()
+            .flatten()
+            .map(|(layout, gaps)| (format!("gaps of layout '{layout}'"), gaps));
+        let all_gaps = self.gaps.iter().map(|gaps| ("gaps".to_owned(), gaps));
+        for (name, gaps) in all_gaps.chain(layout_gaps) {
+            for (side, gap) in [("inner", gaps.inner), ("outer", gaps.outer)] {
+                if let Some(gap) = gap.filter(|&gap| gap < 0) {
+                    return Err(format!("{name}: {side} is {gap}, it must not be negative").into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn gaps_for(&self, layout: &str) -> (i32, i32) {
+        let global = self.gaps.unwrap_or_default();
+        let gaps = match self.layout_gaps.as_ref().and_then(|lg| lg.get(layout)) {
+            Some(&per_layout) => global + per_layout,
+            None => global,
+        };
+        (gaps.inner.unwrap_or(0), gaps.outer.unwrap_or(0))
+    }
+
+    pub fn transform_for(&self, layout: &str) -> Transform {
+        let transforms = self
+            .layout_transforms
+            .as_ref()
+            .and_then(|lt| lt.get(layout).copied())
+            .unwrap_or_default();
+        Transform {
+            mirror: transforms.mirror.unwrap_or(false),
+            flip: transforms.flip.unwrap_or(false),
+            transpose: transforms.transpose.unwrap_or(false),
+        }
+    }
+
+    /// Whether the window is excluded from tiling by the class, process or
+    /// CoreWindow title lists.
+    pub fn ignores(&self, class_name: &str, process_name: &str, title: &str) -> bool {
+        let core_window = class_name.contains("Windows.UI.Core.CoreWindow")
+            && self
+                .windows_ui_core_corewindow
+                .iter()
+                .flatten()
+                .any(|t| title.contains(t));
+        core_window
+            || self
+                .class_names
+                .iter()
+                .flatten()
+                .any(|cn| class_name.contains(cn))
+            || self
+                .process_names
+                .iter()
+                .flatten()
+                .any(|p| process_name.contains(p))
+    }
+
+    pub fn min_size_for(&self, class_name: &str, process_name: &str, title: &str) -> MinSize {
+        self.min_sizes
+            .iter()
+            .flatten()
+            .filter(|rule| rule.matches(class_name, process_name, title))
+            .fold(MinSize::default(), |min, rule| {
+                min.max(MinSize {
+                    width: rule.width.unwrap_or(0),
+                    height: rule.height.unwrap_or(0),
+                })
+            })
+    }
+
+    pub fn auto_layout_for(&self, windows: usize, orientation: Orientation) -> Option<&str> {
+        self.auto_layout
+            .iter()
+            .flatten()
+            .find(|rule| rule.matches(windows, orientation))
+            .map(|rule| rule.layout.as_str())
+    }
+
+    pub fn custom_layouts(&self) -> Result<Vec<CustomLayout>> {
+        let built_ins = LayoutRegistry::default();
+        let mut layouts = vec![];
+        for (name, node) in self.custom_layouts.iter().flatten() {
+            if built_ins.get(name).is_some() {
+                return Err(
+                    format!("custom layout '{name}' has the name of a built-in layout").into(),
+                );
+            }
+            if node.weight.is_some() {
+                return Err(format!(
+                    "custom layout '{name}': root has `weight` but only children are weighted"
+                )
+                .into());
+            }
+            let root = node
+                .to_node("root")
+                .map_err(|e| format!("custom layout '{name}': {e}"))?;
+            layouts.push(CustomLayout {
+                name: name.clone(),
+                root,
+            });
+        }
+        Ok(layouts)
+    }
+
+    pub fn load_default() -> Result<Self> {
+        let mut config_path = env::current_exe().expect("Failed to get current executable path");
+        config_path.set_file_name("default.yaml");
+        info!("Reading config file from {:?}", config_path);
+        let config_file = File::open(config_path).expect("Could not open config file");
+        let config: Config = serde_yaml::from_reader(config_file).expect("Could not read config");
+        Ok(config)
+    }
+
+    pub fn load_or_create_user_config(self, app_data_path: &Path) -> Result<Self> {
+        if !app_data_path.exists() {
+            create_dir(app_data_path).expect("Could not create directory in appdata");
+        }
+        let mut user_config_path = app_data_path.to_path_buf();
+        user_config_path.push("config.yaml");
+        if !Path::new(&user_config_path.clone().into_os_string()).exists() {
+            let mut template_path = env::current_exe()?;
+            template_path.set_file_name("user.yaml");
+            copy(template_path, user_config_path.clone()).expect("Could not copy user.toml");
+        }
+        let user_config_file = File::open(user_config_path)?;
+        // Reloading a config with a typo must not take the window manager down
+        let user_config: Config = serde_yaml::from_reader(user_config_file)
+            .map_err(|e| format!("Could not parse user config file: {e}"))?;
+        let config = self + user_config;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+fn merge_option_vecs<T>(a: Option<Vec<T>>, b: Option<Vec<T>>) -> Option<Vec<T>> {
+    match (a, b) {
+        (Some(mut v1), Some(v2)) => {
+            v1.extend(v2);
+            Some(v1)
+        }
+        (Some(v1), None) => Some(v1),
+        (None, Some(v2)) => Some(v2),
+        (None, None) => None,
+    }
+}
+
+impl LayoutNode {
+    fn to_node(&self, path: &str) -> std::result::Result<Node, String> {
+        match (&self.children, self.slots) {
+            (Some(_), Some(_)) => Err(format!("{path} has both `children` and `slots`")),
+            (Some(children), None) => {
+                if self.stack.is_some() {
+                    return Err(format!("{path} has `stack` but only slots can be stacked"));
+                }
+                if children.is_empty() {
+                    return Err(format!("{path} has an empty `children` list"));
+                }
+                let direction = match &self.split {
+                    Some(split) => parse_direction(split).map_err(|e| format!("{path}: {e}"))?,
+                    None => return Err(format!("{path} has `children` but no `split`")),
+                };
+                let children = children
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, child)| {
+                        let child_path = format!("{path}.children[{idx}]");
+                        let weight = child.weight.unwrap_or(1.0);
+                        if !weight.is_finite() || weight <= 0.0 {
+                            return Err(format!(
+                                "{child_path} has weight {weight}, it must be positive"
+                            ));
+                        }
+                        Ok((weight, child.to_node(&child_path)?))
+                    })
+                    .collect::<std::result::Result<Vec<_>, String>>()?;
+                Ok(Node::Split {
+                    direction,
+                    children,
+                })
+            }
+            (None, capacity) => {
+                if self.split.is_some() {
+                    return Err(format!("{path} has `split` but no `children`"));
+                }
+                if capacity == Some(0) {
+                    return Err(format!(
+                        "{path} has 0 slots, it must hold at least 1 window"
+                    ));
+                }
+                let stack = match &self.stack {
+                    Some(stack) => parse_direction(stack).map_err(|e| format!("{path}: {e}"))?,
+                    None => Direction::Vertical,
+                };
+                Ok(Node::Slots { capacity, stack })
+            }
+        }
+    }
+}
+
+fn parse_direction(value: &str) -> std::result::Result<Direction, String> {
+    match value {
+        "horizontal" => Ok(Direction::Horizontal),
+        "vertical" => Ok(Direction::Vertical),
+        _ => Err(format!(
+            "unknown direction '{value}', expected horizontal or vertical"
+        )),
+    }
+}
+
+fn merge_option_maps<M: IntoIterator + Extend<M::Item>>(a: Option<M>, b: Option<M>) -> Option<M> {
+    match (a, b) {
+        (Some(mut m1), Some(m2)) => {
+            m1.extend(m2);
+            Some(m1)
+        }
+        (a, b) => a.or(b),
+    }
+}
+
+fn merge_option<T>(lhs: Option<T>, rhs: Option<T>) -> Option<T> {
+    match (lhs, rhs) {
+        (Some(_), Some(b)) => Some(b),
+        (Some(a), None) => Some(a),
+        (None, Some(b)) => Some(b),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Result<Vec<CustomLayout>> {
+        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.custom_layouts()
+    }
+
+    #[test]
+    fn custom_layout_parses() {
+        let layouts = parse(
+            "
+custom_layouts:
+  Editor:
+    split: horizontal
+    children:
+      - weight: 60
+        slots: 1
+      - weight: 40
+        stack: vertical
+",
+        )
+        .unwrap();
+        assert_eq!(layouts.len(), 1);
+        assert_eq!(layouts[0].name, "Editor");
+        assert!(matches!(
+            &layouts[0].root,
+            Node::Split { direction: Direction::Horizontal, children } if children.len() == 2
+        ));
+    }
+
+    #[test]
+    fn custom_layouts_are_sorted_by_name() {
+        let layouts = parse(
+            "
+custom_layouts:
+  Zen:
+    slots: 1
+  Editor:
+    slots: 2
+  Mosaic:
+    slots: 4
+",
+        )
+        .unwrap();
+        let names: Vec<&str> = layouts.iter().map(|layout| layout.name.as_str()).collect();
+        assert_eq!(names, ["Editor", "Mosaic", "Zen"]);
+    }
+
+    #[test]
+    fn custom_layout_errors_name_the_node() {
+        let err = parse(
+            "
+custom_layouts:
+  Broken:
+    split: horizontal
+    children:
+      - slots: 1
+      - split: diagonal
+        children:
+          - slots: 1
+",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "custom layout 'Broken': root.children[1]: unknown direction 'diagonal', expected horizontal or vertical"
+        );
+    }
+
+    #[test]
+    fn custom_layout_rejects_invalid_nodes() {
+        let cases = [
+            ("split: vertical", "root has `split` but no `children`"),
+            (
+                "slots: 0",
+                "root has 0 slots, it must hold at least 1 window",
+            ),
+            (
+                "children: []\n    split: vertical",
+                "root has an empty `children` list",
+            ),
+            (
+                "children:\n      - slots: 1",
+                "root has `children` but no `split`",
+            ),
+            (
+                "split: vertical\n    children:\n      - weight: -1",
+                "root.children[0] has weight -1, it must be positive",
+            ),
+            (
+                "weight: 2\n    slots: 1",
+                "root has `weight` but only children are weighted",
+            ),
+        ];
+        for (node, message) in cases {
+            let err = parse(&format!("custom_layouts:\n  Broken:\n    {node}\n")).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("custom layout 'Broken': {message}")
+            );
+        }
+    }
+
+    #[test]
+    fn custom_layout_rejects_unknown_keys() {
+        let err = serde_yaml::from_str::<Config>(
+            "
+custom_layouts:
+  Typo:
+    split: horizontal
+    children:
+      - slot: 2
+",
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("unknown field `slot`"), "{err}");
+    }
+
+    #[test]
+    fn transform_for_layout() {
+        let config: Config =
+            serde_yaml::from_str("layout_transforms:\n  Dwindle:\n    mirror: true\n").unwrap();
+        assert_eq!(
+            config.transform_for("Dwindle"),
+            Transform {
+                mirror: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(config.transform_for("Grid"), Transform::default());
+    }
+
+    #[test]
+    fn min_size_rules_combine() {
+        let config: Config = serde_yaml::from_str(
+            "
+min_sizes:
+  - process_name: ms-teams.exe
+    width: 720
+  - process_name: ms-teams.exe
+    title: Meeting
+    height: 500
+  - class_name: ApplicationFrameWindow
+    width: 400
+    height: 300
+",
+        )
+        .unwrap();
+        assert_eq!(
+            config.min_size_for("TeamsWebView", "ms-teams.exe", "Meeting with Ana"),
+            MinSize {
+                width: 720,
+                height: 500
+            }
+        );
+        assert_eq!(
+            config.min_size_for("TeamsWebView", "ms-teams.exe", "Chat"),
+            MinSize {
+                width: 720,
+                height: 0
+            }
+        );
+        assert_eq!(
+            config.min_size_for("Notepad", "notepad.exe", "Untitled"),
+            MinSize::default()
+        );
+    }
+
+    #[test]
+    fn ignore_lists_match_substrings() {
+        let config: Config = serde_yaml::from_str(
+            "
+Windows.UI.Core.CoreWindow:
+  - Start
+class_names:
+  - WorkerW
+process_names:
+  - SearchHost
+",
+        )
+        .unwrap();
+        assert!(config.ignores("WorkerW", "explorer.exe", "Desktop"));
+        assert!(config.ignores("Chrome_WidgetWin_1", "SearchHost.exe", "Search"));
+        assert!(config.ignores("Windows.UI.Core.CoreWindow", "app.exe", "Start"));
+        assert!(!config.ignores("Windows.UI.Core.CoreWindow", "app.exe", "Calculator"));
+        assert!(!config.ignores("Notepad", "notepad.exe", "Start"));
+    }
+
+    #[test]
+    fn auto_layout_picks_first_matching_rule() {
+        let config: Config = serde_yaml::from_str(
+            "
+auto_layout:
+  - orientation: portrait
+    layout: Rows
+  - max_windows: 1
+    layout: Monocle
+  - min_windows: 2
+    max_windows: 3
+    layout: Columns
+  - min_windows: 4
+    layout: Dwindle
+",
+        )
+        .unwrap();
+        let landscape = Orientation::Landscape;
+        assert_eq!(config.auto_layout_for(1, landscape), Some("Monocle"));
+        assert_eq!(config.auto_layout_for(3, landscape), Some("Columns"));
+        assert_eq!(config.auto_layout_for(7, landscape), Some("Dwindle"));
+        assert_eq!(
+            config.auto_layout_for(1, Orientation::Portrait),
+            Some("Rows")
+        );
+        let config: Config = serde_yaml::from_str("layout: Grid").unwrap();
+        assert_eq!(config.auto_layout_for(1, landscape), None);
+    }
+
+    #[test]
+    fn custom_layout_cannot_shadow_built_in() {
+        let err = parse("custom_layouts:\n  Grid:\n    slots: 1\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "custom layout 'Grid' has the name of a built-in layout"
+        );
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        let config: Config = serde_yaml::from_str(include_str!("../../default.yaml")).unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn negative_gaps_are_rejected() {
+        let cases = [
+            (
+                "gaps: {inner: -4}",
+                "gaps: inner is -4, it must not be negative",
+            ),
+            (
+                "gaps: {inner: 4, outer: -1}",
+                "gaps: outer is -1, it must not be negative",
+            ),
+            (
+                "layout_gaps: {Monocle: {outer: -8}}",
+                "gaps of layout 'Monocle': outer is -8, it must not be negative",
+            ),
+        ];
+        for (yaml, expected) in cases {
+            let config: Config = serde_yaml::from_str(yaml).unwrap();
+            assert_eq!(config.validate().unwrap_err().to_string(), expected);
+        }
+        let config: Config = serde_yaml::from_str("gaps: {inner: 0, outer: 8}").unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn ratios_out_of_range_are_rejected() {
+        let cases = [
+            (
+                "master_ratio: 0.0",
+                "master_ratio is 0, it must be between 0.1 and 0.9",
+            ),
+            (
+                "master_ratio: 1.5",
+                "master_ratio is 1.5, it must be between 0.1 and 0.9",
+            ),
+            (
+                "master_ratio: .nan",
+                "master_ratio is NaN, it must be between 0.1 and 0.9",
+            ),
+            (
+                "stack_balance: -0.5",
+                "stack_balance is -0.5, it must be between 0 and 1",
+            ),
+        ];
+        for (yaml, expected) in cases {
+            let config: Config = serde_yaml::from_str(yaml).unwrap();
+            assert_eq!(config.validate().unwrap_err().to_string(), expected);
+        }
+        let config: Config = serde_yaml::from_str("master_ratio: 0.9\nstack_balance: 0.0").unwrap();
+        assert!(config.validate().is_ok());
+    }
+}
@@ -0,0 +1,387 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    bsp::BspTree,
+    config::Orientation,
+    layout::{LayoutEngine, LayoutParams, Transform},
+    strip::Strip,
+};
+
+/// Virtual desktop identifier, the platform's GUID as a number.
+pub type DesktopId = u128;
+
+pub trait DesktopProvider<W> {
+    fn desktop_of(&self, window: W) -> Option<DesktopId>;
+    fn is_on_current_desktop(&self, window: W) -> bool;
+    /// Desktop the user is looking at, when the platform can tell.
+    fn current_desktop(&self) -> Option<DesktopId>;
+}
+
+#[derive(Clone)]
+pub struct DesktopState<W> {
+    pub layout: Rc<dyn LayoutEngine>,
+    pub layout_params: HashMap<String, LayoutParams>,
+    pub transforms: HashMap<String, Transform>,
+    pub auto_layout_state: Option<(usize, Orientation)>,
+    pub strip: Strip<W>,
+    pub bsp: BspTree<W>,
+    pub windows: Vec<W>,
+}
+
+impl<W: Copy + PartialEq> DesktopState<W> {
+    pub fn new(layout: Rc<dyn LayoutEngine>, strip: Strip<W>) -> Self {
+        DesktopState {
+            layout,
+            layout_params: Default::default(),
+            transforms: Default::default(),
+            auto_layout_state: None,
+            strip,
+            bsp: BspTree::new(),
+            windows: vec![],
+        }
+    }
+
+    fn add_window(&mut self, window: W, split_at: Option<W>) {
+        self.windows.push(window);
+        self.bsp.insert(window, split_at);
+    }
+
+    fn remove_window(&mut self, window: W) {
+        self.windows.retain(|&w| w != window);
+        self.strip.forget(window);
+        self.bsp.remove(window);
+    }
+
+    pub fn swap_windows(&mut self, a: W, b: W) {
+        let a_idx = self.windows.iter().position(|&w| w == a);
+        let b_idx = self.windows.iter().position(|&w| w == b);
+        if let (Some(a_idx), Some(b_idx)) = (a_idx, b_idx) {
+            self.windows.swap(a_idx, b_idx);
+        }
+    }
+
+    pub fn move_window(&mut self, window: W, to: W) {
+        let from_idx = self.windows.iter().position(|&w| w == window);
+        let to_idx = self.windows.iter().position(|&w| w == to);
+        if let (Some(from_idx), Some(to_idx)) = (from_idx, to_idx) {
+            let moved = self.windows.remove(from_idx);
+            self.windows.insert(to_idx, moved);
+        }
+    }
+}
+
+/// Layout state for every monitor of every virtual desktop, keyed by the
+/// desktop's id and the monitor's index.
+pub struct Desktops<W> {
+    template: DesktopState<W>,
+    states: HashMap<(DesktopId, usize), DesktopState<W>>,
+    current: DesktopId,
+}
+
+impl<W: Copy + PartialEq> Desktops<W> {
+    pub fn new(template: DesktopState<W>) -> Self {
+        Desktops {
+            template,
+            states: Default::default(),
+            current: 0,
+        }
+    }
+
+    pub fn current(&self, monitor: usize) -> &DesktopState<W> {
+        self.states
+            .get(&(self.current, monitor))
+            .unwrap_or(&self.template)
+    }
+
+    pub fn current_mut(&mut self, monitor: usize) -> &mut DesktopState<W> {
+        self.states
+            .entry((self.current, monitor))
+            .or_insert_with(|| self.template.clone())
+    }
+
+    /// Every desktop's state, along with the one new desktops start from.
+    pub fn states_mut(&mut self) -> impl Iterator<Item = &mut DesktopState<W>> {
+        std::iter::once(&mut self.template).chain(self.states.values_mut())
+    }
+
+    /// Monitor the window was last filed under on the current desktop.
+    pub fn monitor_of(&self, window: W) -> Option<usize> {
+        self.states
+            .iter()
+            .find(|(&(id, _), state)| id == self.current && state.windows.contains(&window))
+            .map(|(&(_, monitor), _)| monitor)
+    }
+
+    /// Monitor the window is filed under, whichever desktop it's on.
+    pub fn filed_monitor(&self, window: W) -> Option<usize> {
+        self.states
+            .iter()
+            .find(|(_, state)| state.windows.contains(&window))
+            .map(|(&(_, monitor), _)| monitor)
+    }
+
+    /// Moves the window to the end of `monitor`'s order on the current
+    /// desktop.
+    pub fn move_to_monitor(&mut self, window: W, monitor: usize) {
+        for (&(id, _), state) in self.states.iter_mut() {
+            if id == self.current {
+                state.remove_window(window);
+            }
+        }
+        self.current_mut(monitor).add_window(window, None);
+    }
+
+    /// Files `managed` windows under the desktop they're on and the monitor
+    /// paired with them, keeping the order they already have there, and
+    /// takes the current desktop from the provider. When it can't tell, the
+    /// current desktop is the one the first window on it is on.
+    /// Windows new to a desktop split its tiling tree at `split_at` when
+    /// that window is in it.
+    pub fn sync<P: DesktopProvider<W> + ?Sized>(
+        &mut self,
+        provider: &P,
+        managed: &[(W, usize)],
+        split_at: Option<W>,
+    ) {
+        for state in self.states.values_mut() {
+            for window in state.windows.clone() {
+                if !managed.iter().any(|&(w, _)| w == window) {
+                    state.remove_window(window);
+                }
+            }
+        }
+        for &(window, monitor) in managed {
+            let Some(id) = provider.desktop_of(window) else {
+                continue;
+            };
+            for (&key, state) in self.states.iter_mut() {
+                if key != (id, monitor) && state.windows.contains(&window) {
+                    state.remove_window(window);
+                }
+            }
+            let state = self
+                .states
+                .entry((id, monitor))
+                .or_insert_with(|| self.template.clone());
+            if !state.windows.contains(&window) {
+                state.add_window(window, split_at);
+            }
+        }
+        if let Some(id) = provider.current_desktop().or_else(|| {
+            managed
+                .iter()
+                .find(|&&(window, _)| provider.is_on_current_desktop(window))
+                .and_then(|&(window, _)| provider.desktop_of(window))
+        }) {
+            self.current = id;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::{layout::LayoutRegistry, strip::DEFAULT_WIDTH};
+
+    #[derive(Default)]
+    struct FakeDesktops {
+        windows: RefCell<HashMap<isize, DesktopId>>,
+        current: RefCell<DesktopId>,
+    }
+
+    impl FakeDesktops {
+        fn place(&self, hwnd: isize, desktop: u128) {
+            self.windows.borrow_mut().insert(hwnd, desktop);
+        }
+
+        fn switch_to(&self, desktop: u128) {
+            *self.current.borrow_mut() = desktop;
+        }
+    }
+
+    impl DesktopProvider<isize> for FakeDesktops {
+        fn desktop_of(&self, hwnd: isize) -> Option<DesktopId> {
+            self.windows.borrow().get(&hwnd).copied()
+        }
+
+        fn is_on_current_desktop(&self, hwnd: isize) -> bool {
+            self.desktop_of(hwnd) == Some(*self.current.borrow())
+        }
+
+        fn current_desktop(&self) -> Option<DesktopId> {
+            Some(*self.current.borrow())
+        }
+    }
+
+    fn desktops() -> (Desktops<isize>, FakeDesktops) {
+        let layout = LayoutRegistry::default().get("Dwindle").unwrap();
+        let strip = Strip::new(DEFAULT_WIDTH);
+        (
+            Desktops::new(DesktopState::new(layout, strip)),
+            FakeDesktops::default(),
+        )
+    }
+
+    fn on_monitor(ids: &[isize], monitor: usize) -> Vec<(isize, usize)> {
+        ids.iter().map(|&id| (id, monitor)).collect()
+    }
+
+    #[test]
+    fn layout_is_kept_per_desktop() {
+        let (mut desktops, fake) = desktops();
+        fake.place(1, 1);
+        fake.place(2, 2);
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1, 2], 0), None);
+        desktops.current_mut(0).layout = LayoutRegistry::default().get("Monocle").unwrap();
+        fake.switch_to(2);
+        desktops.sync(&fake, &on_monitor(&[1, 2], 0), None);
+        assert_eq!(desktops.current(0).layout.name(), "Dwindle");
+        assert_eq!(desktops.current(0).windows, vec![2]);
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1, 2], 0), None);
+        assert_eq!(desktops.current(0).layout.name(), "Monocle");
+        assert_eq!(desktops.current(0).windows, vec![1]);
+    }
+
+    #[test]
+    fn window_order_is_kept_per_desktop() {
+        let (mut desktops, fake) = desktops();
+        for hwnd in 1..=4 {
+            fake.place(hwnd, 1);
+        }
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1, 2, 3, 4], 0), None);
+        desktops.current_mut(0).move_window(4, 1);
+        desktops.sync(&fake, &on_monitor(&[1, 2, 3, 4], 0), None);
+        assert_eq!(desktops.current(0).windows, vec![4, 1, 2, 3]);
+    }
+
+    #[test]
+    fn swapped_windows_trade_places() {
+        let (mut desktops, fake) = desktops();
+        for hwnd in 1..=3 {
+            fake.place(hwnd, 1);
+        }
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1, 2, 3], 0), None);
+        desktops.current_mut(0).swap_windows(3, 1);
+        assert_eq!(desktops.current(0).windows, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn window_moved_to_another_desktop_follows() {
+        let (mut desktops, fake) = desktops();
+        fake.place(1, 1);
+        fake.place(2, 1);
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1, 2], 0), None);
+        fake.place(2, 2);
+        desktops.sync(&fake, &on_monitor(&[1, 2], 0), None);
+        assert_eq!(desktops.current(0).windows, vec![1]);
+        fake.switch_to(2);
+        desktops.sync(&fake, &on_monitor(&[1, 2], 0), None);
+        assert_eq!(desktops.current(0).windows, vec![2]);
+    }
+
+    #[test]
+    fn unmanaged_windows_are_dropped() {
+        let (mut desktops, fake) = desktops();
+        fake.place(1, 1);
+        fake.place(2, 1);
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1, 2], 0), None);
+        desktops.sync(&fake, &on_monitor(&[2], 0), None);
+        assert_eq!(desktops.current(0).windows, vec![2]);
+    }
+
+    #[test]
+    fn changes_on_an_empty_desktop_stay_there() {
+        let (mut desktops, fake) = desktops();
+        fake.place(1, 1);
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1], 0), None);
+        fake.switch_to(2);
+        desktops.sync(&fake, &on_monitor(&[1], 0), None);
+        assert!(desktops.current(0).windows.is_empty());
+        desktops.current_mut(0).layout = LayoutRegistry::default().get("Monocle").unwrap();
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1], 0), None);
+        assert_eq!(desktops.current(0).layout.name(), "Dwindle");
+        assert_eq!(desktops.current(0).windows, vec![1]);
+    }
+
+    #[test]
+    fn monitors_keep_their_own_state() {
+        let (mut desktops, fake) = desktops();
+        for hwnd in 1..=3 {
+            fake.place(hwnd, 1);
+        }
+        fake.switch_to(1);
+        let mut managed = on_monitor(&[1, 2], 0);
+        managed.extend(on_monitor(&[3], 1));
+        desktops.sync(&fake, &managed, None);
+        desktops.current_mut(1).layout = LayoutRegistry::default().get("Monocle").unwrap();
+        assert_eq!(desktops.current(0).windows, vec![1, 2]);
+        assert_eq!(desktops.current(1).windows, vec![3]);
+        assert_eq!(desktops.current(0).layout.name(), "Dwindle");
+        assert_eq!(desktops.current(1).layout.name(), "Monocle");
+        assert_eq!(desktops.monitor_of(3), Some(1));
+    }
+
+    #[test]
+    fn tiling_tree_is_kept_per_monitor() {
+        let (mut desktops, fake) = desktops();
+        for hwnd in 1..=4 {
+            fake.place(hwnd, 1);
+        }
+        fake.switch_to(1);
+        desktops.sync(&fake, &[(1, 0), (2, 0), (3, 1)], None);
+        desktops.sync(&fake, &[(1, 0), (2, 0), (3, 1), (4, 1)], Some(1));
+        assert_eq!(desktops.current(0).bsp.keys(), vec![1, 2]);
+        assert_eq!(desktops.current(1).bsp.keys(), vec![3, 4]);
+        desktops.sync(&fake, &[(1, 0), (3, 1), (4, 1)], None);
+        assert_eq!(desktops.current(0).bsp.keys(), vec![1]);
+    }
+
+    #[test]
+    fn window_dragged_to_another_monitor_follows() {
+        let (mut desktops, fake) = desktops();
+        for hwnd in 1..=3 {
+            fake.place(hwnd, 1);
+        }
+        fake.switch_to(1);
+        desktops.sync(&fake, &on_monitor(&[1, 2, 3], 0), None);
+        desktops.sync(&fake, &[(1, 0), (2, 1), (3, 0)], None);
+        assert_eq!(desktops.current(0).windows, vec![1, 3]);
+        assert_eq!(desktops.current(1).windows, vec![2]);
+    }
+
+    #[test]
+    fn filed_monitor_is_found_on_any_desktop() {
+        let (mut desktops, fake) = desktops();
+        fake.place(1, 1);
+        fake.place(2, 2);
+        fake.switch_to(1);
+        desktops.sync(&fake, &[(1, 0), (2, 1)], None);
+        assert_eq!(desktops.monitor_of(2), None);
+        assert_eq!(desktops.filed_monitor(2), Some(1));
+        assert_eq!(desktops.filed_monitor(3), None);
+    }
+
+    #[test]
+    fn window_sent_to_monitor_joins_the_end_of_its_order() {
+        let (mut desktops, fake) = desktops();
+        for hwnd in 1..=3 {
+            fake.place(hwnd, 1);
+        }
+        fake.switch_to(1);
+        desktops.sync(&fake, &[(1, 0), (2, 1), (3, 0)], None);
+        desktops.move_to_monitor(1, 1);
+        assert_eq!(desktops.current(0).windows, vec![3]);
+        assert_eq!(desktops.current(1).windows, vec![2, 1]);
+        assert_eq!(desktops.monitor_of(1), Some(1));
+    }
+}
@@ -0,0 +1,148 @@
+use std::{env, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{Rect, Result};
+
+/// Named pipe the window manager listens on under Windows.
+pub const PIPE_NAME: &str = r"\\.\pipe\grout-wm";
+
+/// Unix domain socket standing in for the named pipe elsewhere.
+pub fn socket_path() -> PathBuf {
+    env::temp_dir().join("grout-wm.sock")
+}
+
+/// Command sent by a client, one JSON object per line.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    SetLayout {
+        layout: String,
+    },
+    /// Swaps the focused window with the next one on its monitor.
+    Swap,
+    FocusNext,
+    ToggleFloat,
+    ReloadConfig,
+    QueryState,
+    /// Asks why a window is or isn't tiled.
+    Explain {
+        hwnd: isize,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum Response {
+    Ok,
+    State(State),
+    Explanation(Explanation),
+    Error { message: String },
+}
+
+impl Response {
+    pub fn error(message: impl Into<String>) -> Self {
+        Response::Error {
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct State {
+    pub layouts: Vec<String>,
+    pub focused: Option<isize>,
+    pub monitors: Vec<MonitorState>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct MonitorState {
+    pub layout: String,
+    pub work_area: Rect,
+    /// Windows on screen, tiled ones in tiling order followed by floating ones.
+    pub windows: Vec<WindowState>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WindowState {
+    pub hwnd: isize,
+    pub title: String,
+    pub class_name: String,
+    pub process_name: Option<String>,
+    pub rect: Rect,
+    pub floating: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Explanation {
+    pub hwnd: isize,
+    pub title: String,
+    pub class_name: String,
+    pub process_name: Option<String>,
+    pub managed: bool,
+    pub floating: bool,
+    pub minimized: bool,
+    pub on_current_desktop: bool,
+    /// Monitor the window is tiled on.
+    pub monitor: Option<usize>,
+    /// Reasons the window is not managed, empty when it is.
+    pub rejections: Vec<String>,
+}
+
+/// Encodes a request or response as a single line.
+pub fn encode<T: Serialize>(message: &T) -> Result<String> {
+    let mut line =
+        serde_json::to_string(message).map_err(|e| format!("Could not encode message: {e}"))?;
+    line.push('\n');
+    Ok(line)
+}
+
+pub fn decode<T: DeserializeOwned>(line: &str) -> Result<T> {
+    serde_json::from_str(line.trim_end()).map_err(|e| format!("Invalid message: {e}").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_tagged_with_their_command() {
+        let line = encode(&Request::SetLayout {
+            layout: "Monocle".to_owned(),
+        })
+        .unwrap();
+        assert_eq!(
+            line,
+            "{\"command\":\"set-layout\",\"layout\":\"Monocle\"}\n"
+        );
+        assert_eq!(
+            decode::<Request>("{\"command\":\"focus-next\"}").unwrap(),
+            Request::FocusNext
+        );
+        assert!(decode::<Request>("{\"command\":\"self-destruct\"}").is_err());
+    }
+
+    #[test]
+    fn responses_survive_the_round_trip() {
+        let state = Response::State(State {
+            layouts: vec!["Dwindle".to_owned()],
+            focused: Some(2),
+            monitors: vec![MonitorState {
+                layout: "Dwindle".to_owned(),
+                work_area: Rect {
+                    left: 0,
+                    top: 0,
+                    right: 1000,
+                    bottom: 600,
+                },
+                windows: vec![],
+            }],
+        });
+        for response in [Response::Ok, state, Response::error("Unknown layout")] {
+            assert_eq!(
+                decode::<Response>(&encode(&response).unwrap()).unwrap(),
+                response
+            );
+        }
+    }
+}
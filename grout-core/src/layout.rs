@@ -0,0 +1,1385 @@
+use std::rc::Rc;
+
+use crate::{
+    bsp::BspTree,
+    strip::{Strip, DEFAULT_WIDTH},
+    Rect, Result,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayoutParams {
+    pub masters: usize,
+    pub main_ratio: f32,
+    pub split_ratios: Vec<f32>,
+    pub stack_balance: f32,
+}
+
+impl Default for LayoutParams {
+    fn default() -> Self {
+        LayoutParams {
+            masters: 1,
+            main_ratio: 0.5,
+            split_ratios: vec![],
+            stack_balance: 0.5,
+        }
+    }
+}
+
+impl LayoutParams {
+    pub fn split_ratio(&self, split: usize) -> f32 {
+        match split {
+            0 => self.main_ratio,
+            _ => self.split_ratios.get(split - 1).copied().unwrap_or(0.5),
+        }
+    }
+
+    pub fn adjust_main_ratio(&mut self, delta: f32) {
+        self.main_ratio = (self.main_ratio + delta).clamp(MIN_RATIO, MAX_RATIO);
+    }
+
+    pub fn adjust_split_ratio(&mut self, split: usize, delta: f32) {
+        if split == 0 {
+            return self.adjust_main_ratio(delta);
+        }
+        if self.split_ratios.len() < split {
+            self.split_ratios.resize(split, 0.5);
+        }
+        let ratio = &mut self.split_ratios[split - 1];
+        *ratio = (*ratio + delta).clamp(MIN_RATIO, MAX_RATIO);
+    }
+
+    pub fn adjust_masters(&mut self, delta: isize) {
+        self.masters = self.masters.saturating_add_signed(delta);
+    }
+}
+
+pub const MIN_RATIO: f32 = 0.1;
+pub const MAX_RATIO: f32 = 0.9;
+
+fn split_point(start: i32, end: i32, ratio: f32) -> i32 {
+    split_point_leaving(start, end, ratio, 1)
+}
+
+/// Like `split_point`, but leaves `rest` pixels on the `end` side when there
+/// is room, for windows that still have to be cut out of it.
+fn split_point_leaving(start: i32, end: i32, ratio: f32, rest: i32) -> i32 {
+    let point = start + ((end - start) as f32 * ratio.clamp(0.0, 1.0)).round() as i32;
+    let length = (end - start).abs();
+    if length < 2 {
+        return point;
+    }
+    // Keep at least a pixel on both sides so small areas never collapse
+    let step = (end - start).signum();
+    let (first, last) = (start + step, end - step * rest.clamp(1, length - 1));
+    point.clamp(first.min(last), first.max(last))
+}
+
+/// Cuts still to be made along the same axis once window `v` has been split
+/// off, by layouts that alternate between the axes.
+fn cuts_after(v: usize, n: usize) -> i32 {
+    ((n - 1 - v) / 2) as i32
+}
+
+pub fn dwindle(bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+    if n == 0 {
+        return vec![];
+    }
+    (1..n).fold(vec![bounds], |acc, v| {
+        let mut my_acc = acc;
+        let bounds = my_acc.pop().unwrap();
+        let vertical = v % 2 != 0;
+        let ratio = params.split_ratio(v - 1);
+        let rest = cuts_after(v, n) + 1;
+        let Rect {
+            left,
+            top,
+            right,
+            bottom,
+        } = bounds;
+        let rs: Vec<Rect> = if vertical {
+            let split = split_point_leaving(left, right, ratio, rest);
+            vec![
+                Rect {
+                    left,
+                    top,
+                    right: split,
+                    bottom,
+                },
+                Rect {
+                    left: split,
+                    top,
+                    right,
+                    bottom,
+                },
+            ]
+        } else {
+            let split = split_point_leaving(top, bottom, ratio, rest);
+            vec![
+                Rect {
+                    left,
+                    top,
+                    right,
+                    bottom: split,
+                },
+                Rect {
+                    left,
+                    top: split,
+                    right,
+                    bottom,
+                },
+            ]
+        };
+        my_acc.extend(rs);
+        my_acc
+    })
+}
+
+fn spiral(bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+    if n == 0 {
+        return vec![];
+    }
+    (1..n).fold(vec![bounds], |acc, v| {
+        let mut my_acc = acc;
+        let bounds = my_acc.pop().unwrap();
+        let ratio = params.split_ratio(v - 1);
+        let rest = cuts_after(v, n) + 1;
+        let Rect {
+            left,
+            top,
+            right,
+            bottom,
+        } = bounds;
+        let rs: Vec<Rect> = match (v - 1) % 4 {
+            0 => {
+                let split = split_point_leaving(left, right, ratio, rest);
+                vec![
+                    Rect {
+                        right: split,
+                        ..bounds
+                    },
+                    Rect {
+                        left: split,
+                        ..bounds
+                    },
+                ]
+            }
+            1 => {
+                let split = split_point_leaving(top, bottom, ratio, rest);
+                vec![
+                    Rect {
+                        bottom: split,
+                        ..bounds
+                    },
+                    Rect {
+                        top: split,
+                        ..bounds
+                    },
+                ]
+            }
+            2 => {
+                let split = split_point_leaving(right, left, ratio, rest);
+                vec![
+                    Rect {
+                        left: split,
+                        ..bounds
+                    },
+                    Rect {
+                        right: split,
+                        ..bounds
+                    },
+                ]
+            }
+            _ => {
+                let split = split_point_leaving(bottom, top, ratio, rest);
+                vec![
+                    Rect {
+                        top: split,
+                        ..bounds
+                    },
+                    Rect {
+                        bottom: split,
+                        ..bounds
+                    },
+                ]
+            }
+        };
+        my_acc.extend(rs);
+        my_acc
+    })
+}
+
+fn monocle(bounds: Rect, n: usize) -> Vec<Rect> {
+    vec![bounds; n]
+}
+
+/// Splits `start..end` into one span per weight. Pixels left over after
+/// rounding down go to the spans that lost the most, earlier spans first on
+/// ties, so the spans always tile the range exactly.
+fn split_span(start: i32, end: i32, weights: &[f32]) -> Vec<(i32, i32)> {
+    let total: f32 = weights.iter().sum();
+    let length = (end - start) as f64;
+    let exact: Vec<f64> = weights
+        .iter()
+        .map(|&w| match total > 0.0 {
+            true => length * w as f64 / total as f64,
+            false => length / weights.len() as f64,
+        })
+        .collect();
+    let mut sizes: Vec<i32> = exact.iter().map(|e| e.floor() as i32).collect();
+    let leftover = (end - start) - sizes.iter().sum::<i32>();
+    let mut by_loss: Vec<usize> = (0..weights.len()).collect();
+    by_loss
+        .sort_by(|&a, &b| (exact[b] - exact[b].floor()).total_cmp(&(exact[a] - exact[a].floor())));
+    for &i in by_loss.iter().take(leftover.max(0) as usize) {
+        sizes[i] += 1;
+    }
+    let mut edge = start;
+    sizes
+        .into_iter()
+        .map(|size| {
+            let span = (edge, edge + size);
+            edge += size;
+            span
+        })
+        .collect()
+}
+
+fn columns(bounds: Rect, n: usize) -> Vec<Rect> {
+    split_span(bounds.left, bounds.right, &vec![1.0; n])
+        .into_iter()
+        .map(|(left, right)| Rect {
+            left,
+            right,
+            ..bounds
+        })
+        .collect()
+}
+
+fn rows(bounds: Rect, n: usize) -> Vec<Rect> {
+    split_span(bounds.top, bounds.bottom, &vec![1.0; n])
+        .into_iter()
+        .map(|(top, bottom)| Rect {
+            top,
+            bottom,
+            ..bounds
+        })
+        .collect()
+}
+
+fn master_stack(bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+    let masters = params.masters;
+    if masters == 0 || n <= masters {
+        return rows(bounds, n);
+    }
+    let split = split_point(bounds.left, bounds.right, params.main_ratio);
+    let mut rs = rows(
+        Rect {
+            right: split,
+            ..bounds
+        },
+        masters,
+    );
+    rs.extend(rows(
+        Rect {
+            left: split,
+            ..bounds
+        },
+        n - masters,
+    ));
+    rs
+}
+
+fn bottom_stack(bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+    let masters = params.masters;
+    if masters == 0 || n <= masters {
+        return columns(bounds, n);
+    }
+    let split = split_point(bounds.top, bounds.bottom, params.main_ratio);
+    let mut rs = columns(
+        Rect {
+            bottom: split,
+            ..bounds
+        },
+        masters,
+    );
+    rs.extend(columns(
+        Rect {
+            top: split,
+            ..bounds
+        },
+        n - masters,
+    ));
+    rs
+}
+
+fn grid(bounds: Rect, n: usize) -> Vec<Rect> {
+    if n == 0 {
+        return vec![];
+    }
+    let width = (bounds.right - bounds.left).max(1) as f32;
+    let height = (bounds.bottom - bounds.top).max(1) as f32;
+    let (number_of_rows, number_of_columns) = (1..=n)
+        .map(|r| (r, n.div_ceil(r)))
+        .filter(|&(r, c)| (r - 1) * c < n)
+        .min_by(|&(r1, c1), &(r2, c2)| {
+            let skew = |r: usize, c: usize| ((width / c as f32) / (height / r as f32)).ln().abs();
+            skew(r1, c1).total_cmp(&skew(r2, c2))
+        })
+        .unwrap();
+    rows(bounds, number_of_rows)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, row)| {
+            let cells = number_of_columns.min(n - i * number_of_columns);
+            columns(row, cells)
+        })
+        .collect()
+}
+
+fn three_column(bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+    let masters = params.masters.max(1);
+    if n <= masters {
+        return rows(bounds, n);
+    }
+    let balance = params.stack_balance.clamp(0.0, 1.0);
+    let mut right_count = 0;
+    let on_right: Vec<bool> = (0..n - masters)
+        .map(|i| {
+            let is_right = right_count < ((i + 1) as f32 * balance).ceil() as usize;
+            right_count += is_right as usize;
+            is_right
+        })
+        .collect();
+    let left_count = on_right.len() - right_count;
+    let width = bounds.right - bounds.left;
+    let side_width = ((width as f32 * (1.0 - params.main_ratio.clamp(0.0, 1.0)) / 2.0).round()
+        as i32)
+        .clamp(1, ((width - 1) / 2).max(1));
+    let centre_left = match left_count {
+        0 => bounds.left,
+        _ => bounds.left + side_width,
+    };
+    let centre_right = match right_count {
+        0 => bounds.right,
+        _ => bounds.right - side_width,
+    };
+    let mut rs = rows(
+        Rect {
+            left: centre_left,
+            right: centre_right,
+            ..bounds
+        },
+        masters,
+    );
+    let mut right = rows(
+        Rect {
+            left: centre_right,
+            ..bounds
+        },
+        right_count,
+    )
+    .into_iter();
+    let mut left = rows(
+        Rect {
+            right: centre_left,
+            ..bounds
+        },
+        left_count,
+    )
+    .into_iter();
+    rs.extend(on_right.into_iter().filter_map(|is_right| match is_right {
+        true => right.next(),
+        false => left.next(),
+    }));
+    rs
+}
+
+fn focus(bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+    let params = LayoutParams {
+        masters: 1,
+        stack_balance: 0.5,
+        ..params.clone()
+    };
+    three_column(bounds, n, &params)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Clone, Debug)]
+pub enum Node {
+    Split {
+        direction: Direction,
+        children: Vec<(f32, Node)>,
+    },
+    Slots {
+        capacity: Option<usize>,
+        stack: Direction,
+    },
+}
+
+impl Node {
+    fn capacities(&self) -> Vec<Option<usize>> {
+        match self {
+            Node::Split { children, .. } => children
+                .iter()
+                .flat_map(|(_, child)| child.capacities())
+                .collect(),
+            Node::Slots { capacity, .. } => vec![*capacity],
+        }
+    }
+
+    fn place(&self, bounds: Rect, counts: &[usize], out: &mut Vec<Rect>) {
+        match self {
+            Node::Slots { stack, .. } => out.extend(match stack {
+                Direction::Horizontal => columns(bounds, counts[0]),
+                Direction::Vertical => rows(bounds, counts[0]),
+            }),
+            Node::Split {
+                direction,
+                children,
+            } => {
+                let mut offset = 0;
+                let occupied: Vec<_> = children
+                    .iter()
+                    .map(|(weight, child)| {
+                        let leaves = child.capacities().len();
+                        let child_counts = &counts[offset..offset + leaves];
+                        offset += leaves;
+                        (*weight, child, child_counts)
+                    })
+                    .filter(|(_, _, child_counts)| child_counts.iter().any(|&c| c > 0))
+                    .collect();
+                let weights: Vec<f32> = occupied.iter().map(|(weight, _, _)| *weight).collect();
+                let (start, end) = match direction {
+                    Direction::Horizontal => (bounds.left, bounds.right),
+                    Direction::Vertical => (bounds.top, bounds.bottom),
+                };
+                let spans = split_span(start, end, &weights);
+                for ((_, child, child_counts), (start, end)) in occupied.into_iter().zip(spans) {
+                    let child_bounds = match direction {
+                        Direction::Horizontal => Rect {
+                            left: start,
+                            right: end,
+                            ..bounds
+                        },
+                        Direction::Vertical => Rect {
+                            top: start,
+                            bottom: end,
+                            ..bounds
+                        },
+                    };
+                    child.place(child_bounds, child_counts, out);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CustomLayout {
+    pub name: String,
+    pub root: Node,
+}
+
+impl LayoutEngine for CustomLayout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn arrange(&self, bounds: Rect, n: usize, _params: &LayoutParams) -> Vec<Rect> {
+        let capacities = self.root.capacities();
+        let mut remaining = n;
+        let mut counts: Vec<usize> = capacities
+            .iter()
+            .map(|capacity| {
+                let count = capacity.map_or(remaining, |c| c.min(remaining));
+                remaining -= count;
+                count
+            })
+            .collect();
+        if let Some(last) = counts.last_mut() {
+            *last += remaining;
+        }
+        let mut out = Vec::with_capacity(n);
+        if n > 0 {
+            self.root.place(bounds, &counts, &mut out);
+        }
+        out
+    }
+}
+
+pub const BSP_LAYOUT: &str = "Bsp";
+pub const SCROLLING_LAYOUT: &str = "Scrolling";
+
+/// A fresh tiling tree, each window splitting the one before it. The window
+/// manager keeps a tree per desktop instead, this is what it starts out as.
+fn bsp(bounds: Rect, n: usize) -> Vec<Rect> {
+    let mut tree = BspTree::new();
+    for key in 0..n {
+        tree.insert(key, None);
+    }
+    tree.rects(bounds)
+        .into_iter()
+        .map(|(_, rect)| rect)
+        .collect()
+}
+
+pub trait LayoutEngine {
+    fn name(&self) -> &str;
+    fn arrange(&self, bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect>;
+    fn icon(&self) -> Option<&str> {
+        None
+    }
+}
+
+struct BuiltIn {
+    name: &'static str,
+    icon: Option<&'static str>,
+    arrange: fn(Rect, usize, &LayoutParams) -> Vec<Rect>,
+}
+
+impl LayoutEngine for BuiltIn {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn arrange(&self, bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+        (self.arrange)(bounds, n, params)
+    }
+
+    fn icon(&self) -> Option<&str> {
+        self.icon
+    }
+}
+
+pub struct LayoutRegistry {
+    engines: Vec<Rc<dyn LayoutEngine>>,
+}
+
+impl Default for LayoutRegistry {
+    fn default() -> Self {
+        let built_ins = [
+            BuiltIn {
+                name: "Dwindle",
+                icon: Some("dwindle"),
+                arrange: dwindle,
+            },
+            BuiltIn {
+                name: "Monocle",
+                icon: Some("monocle"),
+                arrange: |bounds, n, _| monocle(bounds, n),
+            },
+            BuiltIn {
+                name: "Columns",
+                icon: Some("columns"),
+                arrange: |bounds, n, _| columns(bounds, n),
+            },
+            BuiltIn {
+                name: "Focus",
+                icon: Some("focus"),
+                arrange: focus,
+            },
+            BuiltIn {
+                name: "Grid",
+                icon: Some("grid"),
+                arrange: |bounds, n, _| grid(bounds, n),
+            },
+            BuiltIn {
+                name: "ThreeColumn",
+                icon: None,
+                arrange: three_column,
+            },
+            BuiltIn {
+                name: "Spiral",
+                icon: None,
+                arrange: spiral,
+            },
+            BuiltIn {
+                name: "MasterStack",
+                icon: None,
+                arrange: master_stack,
+            },
+            BuiltIn {
+                name: "BottomStack",
+                icon: None,
+                arrange: bottom_stack,
+            },
+            BuiltIn {
+                name: "Rows",
+                icon: None,
+                arrange: |bounds, n, _| rows(bounds, n),
+            },
+            BuiltIn {
+                name: BSP_LAYOUT,
+                icon: None,
+                arrange: |bounds, n, _| bsp(bounds, n),
+            },
+            BuiltIn {
+                name: SCROLLING_LAYOUT,
+                icon: None,
+                arrange: |bounds, n, _| {
+                    Strip::new(DEFAULT_WIDTH).rects(&(0..n).collect::<Vec<_>>(), bounds)
+                },
+            },
+        ];
+        LayoutRegistry {
+            engines: built_ins
+                .into_iter()
+                .map(|built_in| Rc::new(built_in) as Rc<dyn LayoutEngine>)
+                .collect(),
+        }
+    }
+}
+
+impl LayoutRegistry {
+    pub fn register(&mut self, engine: Rc<dyn LayoutEngine>) -> Result<()> {
+        if self.get(engine.name()).is_some() {
+            return Err(format!("A layout named '{}' already exists", engine.name()).into());
+        }
+        self.engines.push(engine);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<dyn LayoutEngine>> {
+        self.engines.iter().find(|e| e.name() == name).cloned()
+    }
+
+    pub fn get_index(&self, idx: usize) -> Option<Rc<dyn LayoutEngine>> {
+        self.engines.get(idx).cloned()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<dyn LayoutEngine>> {
+        self.engines.iter()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.engines.iter().map(|e| e.name())
+    }
+
+    pub fn next(&self, name: &str) -> Option<Rc<dyn LayoutEngine>> {
+        let idx = self.engines.iter().position(|e| e.name() == name)?;
+        self.get_index((idx + 1) % self.engines.len())
+    }
+
+    pub fn previous(&self, name: &str) -> Option<Rc<dyn LayoutEngine>> {
+        let idx = self.engines.iter().position(|e| e.name() == name)?;
+        self.get_index((idx + self.engines.len() - 1) % self.engines.len())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Transform {
+    pub mirror: bool,
+    pub flip: bool,
+    pub transpose: bool,
+}
+
+impl Transform {
+    /// Arranges `n` windows with `engine` and transforms the result within
+    /// `bounds`. Transposing lays the windows out in bounds with width and
+    /// height swapped and swaps the axes back, so columns become rows.
+    pub fn arrange(
+        &self,
+        engine: &dyn LayoutEngine,
+        bounds: Rect,
+        n: usize,
+        params: &LayoutParams,
+    ) -> Vec<Rect> {
+        let Rect {
+            left,
+            top,
+            right,
+            bottom,
+        } = bounds;
+        let rects = match self.transpose {
+            true => engine
+                .arrange(
+                    Rect {
+                        left,
+                        top,
+                        right: left + (bottom - top),
+                        bottom: top + (right - left),
+                    },
+                    n,
+                    params,
+                )
+                .into_iter()
+                .map(|r| Rect {
+                    left: left + (r.top - top),
+                    top: top + (r.left - left),
+                    right: left + (r.bottom - top),
+                    bottom: top + (r.right - left),
+                })
+                .collect(),
+            false => engine.arrange(bounds, n, params),
+        };
+        rects
+            .into_iter()
+            .map(|r| match self.mirror {
+                true => Rect {
+                    left: left + right - r.right,
+                    right: left + right - r.left,
+                    ..r
+                },
+                false => r,
+            })
+            .map(|r| match self.flip {
+                true => Rect {
+                    top: top + bottom - r.bottom,
+                    bottom: top + bottom - r.top,
+                    ..r
+                },
+                false => r,
+            })
+            .collect()
+    }
+}
+
+pub fn apply_gaps(bounds: Rect, rects: Vec<Rect>, inner: i32, outer: i32) -> Vec<Rect> {
+    let (near, far) = (inner / 2, inner - inner / 2);
+    rects
+        .into_iter()
+        .map(|r| {
+            let left = r.left + if r.left <= bounds.left { outer } else { near };
+            let top = r.top + if r.top <= bounds.top { outer } else { near };
+            let right = r.right - if r.right >= bounds.right { outer } else { far };
+            let bottom = r.bottom
+                - if r.bottom >= bounds.bottom {
+                    outer
+                } else {
+                    far
+                };
+            Rect {
+                left,
+                top,
+                right: right.max(left),
+                bottom: bottom.max(top),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        minsize::{self, MinSize},
+        validate::check_layout,
+    };
+    use proptest::prelude::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn layout(name: &str) -> Rc<dyn LayoutEngine> {
+        LayoutRegistry::default().get(name).unwrap()
+    }
+
+    fn assert_tiles(bounds: Rect, rs: &[Rect]) {
+        assert_eq!(check_layout(bounds, rs.len(), rs, false), vec![]);
+    }
+
+    fn any_bounds(min: i32, max: i32) -> impl Strategy<Value = Rect> {
+        (-2000..2000, -2000..2000, min..max, min..max).prop_map(|(left, top, width, height)| Rect {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+        })
+    }
+
+    fn any_params() -> impl Strategy<Value = LayoutParams> {
+        // Shorter and longer than the number of splits, the rest default
+        let split_ratios = prop::collection::vec(MIN_RATIO..=MAX_RATIO, 0..20);
+        (0..4usize, MIN_RATIO..MAX_RATIO, split_ratios, 0.0..=1.0f32).prop_map(
+            |(masters, main_ratio, split_ratios, stack_balance)| LayoutParams {
+                masters,
+                main_ratio,
+                split_ratios,
+                stack_balance,
+            },
+        )
+    }
+
+    fn any_transform() -> impl Strategy<Value = Transform> {
+        any::<(bool, bool, bool)>().prop_map(|(mirror, flip, transpose)| Transform {
+            mirror,
+            flip,
+            transpose,
+        })
+    }
+
+    fn check_every_layout(
+        bounds: Rect,
+        n: usize,
+        params: &LayoutParams,
+        transform: Transform,
+    ) -> std::result::Result<(), TestCaseError> {
+        let registry = LayoutRegistry::default();
+        for engine in registry.iter().filter(|e| e.name() != SCROLLING_LAYOUT) {
+            let rs = transform.arrange(engine.as_ref(), bounds, n, params);
+            let violations = check_layout(bounds, n, &rs, engine.name() == "Monocle");
+            prop_assert!(
+                violations.is_empty(),
+                "{} with {n} windows in {bounds:?} and {transform:?}: {}",
+                engine.name(),
+                violations
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn every_layout_upholds_invariants(
+            bounds in any_bounds(320, 4000),
+            n in 0..=12usize,
+            params in any_params(),
+            transform in any_transform(),
+        ) {
+            check_every_layout(bounds, n, &params, transform)?;
+        }
+
+        #[test]
+        fn every_layout_upholds_invariants_in_tiny_bounds(
+            bounds in any_bounds(24, 64),
+            n in 0..=16usize,
+            params in any_params(),
+            transform in any_transform(),
+        ) {
+            check_every_layout(bounds, n, &params, transform)?;
+        }
+
+        #[test]
+        fn min_sizes_are_honoured_or_stacked(
+            bounds in any_bounds(320, 2000),
+            mins in prop::collection::vec((0..800, 0..800), 1..=8),
+        ) {
+            let n = mins.len();
+            let min_sizes: Vec<MinSize> = mins
+                .into_iter()
+                .map(|(width, height)| MinSize { width, height })
+                .collect();
+            let registry = LayoutRegistry::default();
+            for engine in registry.iter().filter(|e| e.name() != SCROLLING_LAYOUT) {
+                let rs = engine.arrange(bounds, n, &LayoutParams::default());
+                let solved = minsize::solve(bounds, &rs, &min_sizes);
+                if solved != vec![bounds; n] {
+                    let overlap = engine.name() == "Monocle";
+                    prop_assert_eq!(check_layout(bounds, n, &solved, overlap), vec![]);
+                    for (r, min) in solved.iter().zip(&min_sizes) {
+                        prop_assert!(r.right - r.left >= min.width, "{} {r:?} {min:?}", engine.name());
+                        prop_assert!(r.bottom - r.top >= min.height, "{} {r:?} {min:?}", engine.name());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mirror_puts_master_on_the_right() {
+        let transform = Transform {
+            mirror: true,
+            ..Default::default()
+        };
+        let rs = transform.arrange(
+            layout("MasterStack").as_ref(),
+            rect(100, 0, 1100, 600),
+            3,
+            &LayoutParams {
+                main_ratio: 0.6,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            rs,
+            vec![
+                rect(500, 0, 1100, 600),
+                rect(100, 0, 500, 300),
+                rect(100, 300, 500, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn flip_turns_bottom_stack_upside_down() {
+        let transform = Transform {
+            flip: true,
+            ..Default::default()
+        };
+        let rs = transform.arrange(
+            layout("BottomStack").as_ref(),
+            rect(0, 40, 1000, 640),
+            3,
+            &LayoutParams::default(),
+        );
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 340, 1000, 640),
+                rect(0, 40, 500, 340),
+                rect(500, 40, 1000, 340),
+            ]
+        );
+    }
+
+    #[test]
+    fn transpose_turns_columns_into_rows() {
+        let transform = Transform {
+            transpose: true,
+            ..Default::default()
+        };
+        let bounds = rect(100, 40, 1100, 640);
+        let params = LayoutParams::default();
+        assert_eq!(
+            transform.arrange(layout("Columns").as_ref(), bounds, 3, &params),
+            layout("Rows").arrange(bounds, 3, &params)
+        );
+    }
+
+    #[test]
+    fn spiral_rotates_split_position() {
+        let rs = layout("Spiral").arrange(rect(0, 0, 1600, 800), 5, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 800, 800),
+                rect(800, 0, 1600, 400),
+                rect(1200, 400, 1600, 800),
+                rect(800, 600, 1200, 800),
+                rect(800, 400, 1200, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn spiral_tiles_bounds() {
+        let bounds = rect(37, 11, 1957, 1091);
+        for n in 1..=12 {
+            assert_tiles(
+                bounds,
+                &layout("Spiral").arrange(bounds, n, &LayoutParams::default()),
+            );
+        }
+    }
+
+    #[test]
+    fn dwindle_tiles_bounds() {
+        let bounds = rect(37, 11, 1957, 1091);
+        for n in 1..=12 {
+            assert_tiles(
+                bounds,
+                &layout("Dwindle").arrange(bounds, n, &LayoutParams::default()),
+            );
+        }
+    }
+
+    #[test]
+    fn split_span_distributes_remainder() {
+        assert_eq!(
+            split_span(0, 1920, &[1.0; 7]),
+            vec![
+                (0, 275),
+                (275, 550),
+                (550, 824),
+                (824, 1098),
+                (1098, 1372),
+                (1372, 1646),
+                (1646, 1920),
+            ]
+        );
+        assert_eq!(
+            split_span(10, 20, &[1.0, 2.0, 1.0]),
+            vec![(10, 13), (13, 18), (18, 20)]
+        );
+        assert_eq!(split_span(0, 100, &[]), vec![]);
+    }
+
+    #[test]
+    fn every_layout_covers_bounds_exactly() {
+        let registry = LayoutRegistry::default();
+        for bounds in [rect(0, 0, 1920, 1080), rect(-1917, 37, 0, 1090)] {
+            for engine in registry.iter().filter(|e| e.name() != SCROLLING_LAYOUT) {
+                for n in 1..=32 {
+                    let rs = engine.arrange(bounds, n, &LayoutParams::default());
+                    assert_eq!(
+                        check_layout(bounds, n, &rs, engine.name() == "Monocle"),
+                        vec![],
+                        "{} with {n} windows",
+                        engine.name()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rows_odd_count() {
+        let rs = layout("Rows").arrange(rect(0, 0, 1440, 2560), 3, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 1440, 854),
+                rect(0, 854, 1440, 1707),
+                rect(0, 1707, 1440, 2560),
+            ]
+        );
+    }
+
+    #[test]
+    fn rows_non_zero_origin() {
+        let rs = layout("Rows").arrange(rect(100, 40, 1540, 2600), 2, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![rect(100, 40, 1540, 1320), rect(100, 1320, 1540, 2600)]
+        );
+    }
+
+    #[test]
+    fn rows_no_windows() {
+        assert!(layout("Rows")
+            .arrange(rect(0, 0, 1440, 2560), 0, &LayoutParams::default())
+            .is_empty());
+    }
+
+    #[test]
+    fn bottom_stack_odd_count() {
+        let rs = layout("BottomStack").arrange(rect(0, 0, 1440, 2560), 3, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 1440, 1280),
+                rect(0, 1280, 720, 2560),
+                rect(720, 1280, 1440, 2560),
+            ]
+        );
+    }
+
+    #[test]
+    fn bottom_stack_non_zero_origin() {
+        let params = LayoutParams {
+            masters: 2,
+            main_ratio: 0.6,
+            ..Default::default()
+        };
+        let rs = layout("BottomStack").arrange(rect(100, 40, 1540, 2540), 5, &params);
+        assert_eq!(
+            rs,
+            vec![
+                rect(100, 40, 820, 1540),
+                rect(820, 40, 1540, 1540),
+                rect(100, 1540, 580, 2540),
+                rect(580, 1540, 1060, 2540),
+                rect(1060, 1540, 1540, 2540),
+            ]
+        );
+    }
+
+    #[test]
+    fn bottom_stack_fewer_windows_than_masters() {
+        let params = LayoutParams {
+            masters: 2,
+            main_ratio: 0.6,
+            ..Default::default()
+        };
+        let rs = layout("BottomStack").arrange(rect(100, 40, 1540, 2540), 1, &params);
+        assert_eq!(rs, vec![rect(100, 40, 1540, 2540)]);
+    }
+
+    #[test]
+    fn grid_square_count() {
+        let rs = layout("Grid").arrange(rect(0, 0, 1920, 1080), 4, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 960, 540),
+                rect(960, 0, 1920, 540),
+                rect(0, 540, 960, 1080),
+                rect(960, 540, 1920, 1080),
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_stretches_last_row() {
+        let rs = layout("Grid").arrange(rect(0, 0, 1920, 1080), 5, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 640, 540),
+                rect(640, 0, 1280, 540),
+                rect(1280, 0, 1920, 540),
+                rect(0, 540, 960, 1080),
+                rect(960, 540, 1920, 1080),
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_follows_aspect_ratio() {
+        let rs = layout("Grid").arrange(rect(0, 0, 1440, 2560), 3, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 1440, 854),
+                rect(0, 854, 1440, 1707),
+                rect(0, 1707, 1440, 2560),
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_inset_outer_and_split_inner() {
+        let bounds = rect(0, 0, 1000, 500);
+        let rs = apply_gaps(
+            bounds,
+            layout("Columns").arrange(bounds, 2, &LayoutParams::default()),
+            10,
+            20,
+        );
+        assert_eq!(rs, vec![rect(20, 20, 495, 480), rect(505, 20, 980, 480)]);
+    }
+
+    #[test]
+    fn gaps_odd_inner_keep_full_width_between_windows() {
+        let bounds = rect(100, 50, 1100, 550);
+        let rs = apply_gaps(
+            bounds,
+            layout("Rows").arrange(bounds, 2, &LayoutParams::default()),
+            5,
+            0,
+        );
+        assert_eq!(
+            rs,
+            vec![rect(100, 50, 1100, 297), rect(100, 302, 1100, 550)]
+        );
+    }
+
+    #[test]
+    fn gaps_monocle_only_uses_outer() {
+        let bounds = rect(0, 0, 1000, 500);
+        let rs = apply_gaps(
+            bounds,
+            layout("Monocle").arrange(bounds, 2, &LayoutParams::default()),
+            10,
+            8,
+        );
+        assert_eq!(rs, vec![rect(8, 8, 992, 492); 2]);
+    }
+
+    #[test]
+    fn gaps_never_invert_rects() {
+        let bounds = rect(0, 0, 30, 30);
+        let rs = apply_gaps(bounds, vec![bounds], 0, 20);
+        assert_eq!(rs, vec![rect(20, 20, 20, 20)]);
+    }
+
+    #[test]
+    fn three_column_respects_work_area_origin() {
+        let rs = layout("ThreeColumn").arrange(rect(100, 40, 1100, 640), 5, &Default::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(350, 40, 850, 640),
+                rect(850, 40, 1100, 340),
+                rect(100, 40, 350, 340),
+                rect(850, 340, 1100, 640),
+                rect(100, 340, 350, 640),
+            ]
+        );
+    }
+
+    #[test]
+    fn three_column_distributes_stack_by_balance() {
+        let params = LayoutParams {
+            masters: 2,
+            main_ratio: 0.6,
+            stack_balance: 0.75,
+            ..Default::default()
+        };
+        let rs = layout("ThreeColumn").arrange(rect(0, 0, 1000, 600), 6, &params);
+        assert_eq!(
+            rs,
+            vec![
+                rect(200, 0, 800, 300),
+                rect(200, 300, 800, 600),
+                rect(800, 0, 1000, 200),
+                rect(800, 200, 1000, 400),
+                rect(800, 400, 1000, 600),
+                rect(0, 0, 200, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn three_column_centre_takes_empty_side() {
+        let params = LayoutParams {
+            stack_balance: 1.0,
+            ..Default::default()
+        };
+        let rs = layout("ThreeColumn").arrange(rect(0, 0, 1000, 600), 3, &params);
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 750, 600),
+                rect(750, 0, 1000, 300),
+                rect(750, 300, 1000, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn focus_uses_main_ratio() {
+        let params = LayoutParams {
+            main_ratio: 0.6,
+            ..Default::default()
+        };
+        let rs = layout("Focus").arrange(rect(0, 0, 1000, 600), 3, &params);
+        assert_eq!(
+            rs,
+            vec![
+                rect(200, 0, 800, 600),
+                rect(800, 0, 1000, 600),
+                rect(0, 0, 200, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn dwindle_uses_split_ratios() {
+        let mut params = LayoutParams::default();
+        params.adjust_main_ratio(0.2);
+        params.adjust_split_ratio(1, -0.25);
+        let rs = layout("Dwindle").arrange(rect(0, 0, 1000, 1000), 3, &params);
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 700, 1000),
+                rect(700, 0, 1000, 250),
+                rect(700, 250, 1000, 1000),
+            ]
+        );
+    }
+
+    #[test]
+    fn ratios_are_clamped() {
+        let mut params = LayoutParams::default();
+        params.adjust_main_ratio(1.0);
+        params.adjust_split_ratio(3, -1.0);
+        params.adjust_masters(-2);
+        assert_eq!(params.main_ratio, MAX_RATIO);
+        assert_eq!(params.split_ratios, vec![0.5, 0.5, MIN_RATIO]);
+        assert_eq!(params.masters, 0);
+    }
+
+    fn editor_layout() -> CustomLayout {
+        CustomLayout {
+            name: "Editor".to_owned(),
+            root: Node::Split {
+                direction: Direction::Horizontal,
+                children: vec![
+                    (
+                        60.0,
+                        Node::Slots {
+                            capacity: Some(1),
+                            stack: Direction::Vertical,
+                        },
+                    ),
+                    (
+                        40.0,
+                        Node::Slots {
+                            capacity: None,
+                            stack: Direction::Vertical,
+                        },
+                    ),
+                ],
+            },
+        }
+    }
+
+    #[test]
+    fn custom_fills_slots_in_order() {
+        let rs = editor_layout().arrange(rect(0, 0, 1000, 600), 3, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(0, 0, 600, 600),
+                rect(600, 0, 1000, 300),
+                rect(600, 300, 1000, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn custom_collapses_empty_slots() {
+        let rs = editor_layout().arrange(rect(0, 0, 1000, 600), 1, &LayoutParams::default());
+        assert_eq!(rs, vec![rect(0, 0, 1000, 600)]);
+    }
+
+    #[test]
+    fn custom_overflows_into_last_slot() {
+        let layout = CustomLayout {
+            name: "Pair".to_owned(),
+            root: Node::Split {
+                direction: Direction::Vertical,
+                children: vec![
+                    (
+                        1.0,
+                        Node::Slots {
+                            capacity: Some(1),
+                            stack: Direction::Horizontal,
+                        },
+                    ),
+                    (
+                        1.0,
+                        Node::Slots {
+                            capacity: Some(1),
+                            stack: Direction::Horizontal,
+                        },
+                    ),
+                ],
+            },
+        };
+        let bounds = rect(10, 10, 1010, 610);
+        let rs = layout.arrange(bounds, 3, &LayoutParams::default());
+        assert_eq!(
+            rs,
+            vec![
+                rect(10, 10, 1010, 310),
+                rect(10, 310, 510, 610),
+                rect(510, 310, 1010, 610),
+            ]
+        );
+    }
+
+    #[test]
+    fn registry_cycles_layouts() {
+        let registry = LayoutRegistry::default();
+        assert_eq!(registry.next("Dwindle").unwrap().name(), "Monocle");
+        assert_eq!(registry.previous("Dwindle").unwrap().name(), "Scrolling");
+        assert_eq!(registry.next("Scrolling").unwrap().name(), "Dwindle");
+        assert!(registry.next("Unknown").is_none());
+    }
+
+    #[test]
+    fn registry_rejects_duplicate_names() {
+        let mut registry = LayoutRegistry::default();
+        assert!(registry.register(Rc::new(editor_layout())).is_ok());
+        assert!(registry.register(Rc::new(editor_layout())).is_err());
+        assert_eq!(registry.names().last(), Some("Editor"));
+    }
+}
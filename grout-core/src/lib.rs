@@ -0,0 +1,56 @@
+pub mod bsp;
+pub mod config;
+pub mod desktops;
+pub mod geometry;
+pub mod ipc;
+pub mod layout;
+pub mod minsize;
+pub mod monitors;
+pub mod strip;
+pub mod validate;
+pub mod window;
+
+pub use geometry::{Point, Rect};
+
+pub struct Error {
+    pub(crate) message: String,
+}
+
+impl std::error::Error for Error {}
+
+impl std::fmt::Debug for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = fmt.debug_struct("Error");
+        debug.field("message", &self.message).finish()
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::write!(fmt, "{}", self.message)
+    }
+}
+
+impl std::convert::From<&str> for Error {
+    fn from(err: &str) -> Self {
+        Error {
+            message: String::from(err),
+        }
+    }
+}
+
+impl std::convert::From<String> for Error {
+    fn from(message: String) -> Self {
+        Error { message }
+    }
+}
+
+impl std::convert::From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error {
+            message: err.to_string(),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,177 @@
+use crate::Rect;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MinSize {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl MinSize {
+    pub fn max(self, other: MinSize) -> MinSize {
+        MinSize {
+            width: self.width.max(other.width),
+            height: self.height.max(other.height),
+        }
+    }
+}
+
+/// Moves the edges of `spans` along one axis so every span is at least as
+/// long as its minimum. Edges keep their order, so spans that tiled the
+/// range still tile it, and stay where they were unless a minimum pushes
+/// them. Returns None when the minimums can't fit in `start..end`.
+fn solve_axis(start: i32, end: i32, spans: &[(i32, i32)], mins: &[i32]) -> Option<Vec<(i32, i32)>> {
+    let mut edges: Vec<i32> = spans
+        .iter()
+        .flat_map(|&(a, b)| [a, b])
+        .chain([start, end])
+        .map(|e| e.clamp(start, end))
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+    let index = |e: i32| edges.binary_search(&e.clamp(start, end)).unwrap();
+    let constraints: Vec<(usize, usize, i32)> = spans
+        .iter()
+        .zip(mins)
+        .map(|(&(a, b), &min)| (index(a), index(b), min))
+        .filter(|&(a, b, min)| a < b && min > 0)
+        .collect();
+    let last = edges.len() - 1;
+    // Furthest right each edge can go and still leave room for the minimums
+    // of the spans to its right
+    let mut highest = vec![end; edges.len()];
+    for k in (0..last).rev() {
+        highest[k] = constraints
+            .iter()
+            .filter(|&&(a, _, _)| a == k)
+            .map(|&(_, b, min)| highest[b] - min)
+            .fold(highest[k + 1] - 1, i32::min);
+    }
+    if highest[0] < start {
+        return None;
+    }
+    let mut placed = vec![start; edges.len()];
+    for k in 1..=last {
+        let lowest = constraints
+            .iter()
+            .filter(|&&(_, b, _)| b == k)
+            .map(|&(a, _, min)| placed[a] + min)
+            .fold(placed[k - 1] + 1, i32::max);
+        placed[k] = edges[k].clamp(lowest, highest[k]);
+    }
+    Some(
+        spans
+            .iter()
+            .map(|&(a, b)| (placed[index(a)], placed[index(b)]))
+            .collect(),
+    )
+}
+
+/// Resizes `rects` so each one is at least its minimum size by moving the
+/// edges it shares with its neighbours. When the minimums can't all fit in
+/// `bounds` the windows are stacked on top of each other instead, each
+/// taking the whole of `bounds`.
+pub fn solve(bounds: Rect, rects: &[Rect], min_sizes: &[MinSize]) -> Vec<Rect> {
+    let horizontal: Vec<_> = rects.iter().map(|r| (r.left, r.right)).collect();
+    let vertical: Vec<_> = rects.iter().map(|r| (r.top, r.bottom)).collect();
+    let widths: Vec<_> = min_sizes.iter().map(|m| m.width).collect();
+    let heights: Vec<_> = min_sizes.iter().map(|m| m.height).collect();
+    match (
+        solve_axis(bounds.left, bounds.right, &horizontal, &widths),
+        solve_axis(bounds.top, bounds.bottom, &vertical, &heights),
+    ) {
+        (Some(horizontal), Some(vertical)) => horizontal
+            .into_iter()
+            .zip(vertical)
+            .map(|((left, right), (top, bottom))| Rect {
+                left,
+                top,
+                right,
+                bottom,
+            })
+            .collect(),
+        _ => vec![bounds; rects.len()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn min(width: i32, height: i32) -> MinSize {
+        MinSize { width, height }
+    }
+
+    const BOUNDS: Rect = Rect {
+        left: 100,
+        top: 0,
+        right: 1100,
+        bottom: 600,
+    };
+
+    #[test]
+    fn unconstrained_rects_are_unchanged() {
+        let rects = [rect(100, 0, 600, 600), rect(600, 0, 1100, 600)];
+        assert_eq!(solve(BOUNDS, &rects, &[MinSize::default(); 2]), rects);
+    }
+
+    #[test]
+    fn narrow_window_takes_space_from_its_neighbour() {
+        let rects = [
+            rect(100, 0, 500, 600),
+            rect(500, 0, 600, 600),
+            rect(600, 0, 1100, 600),
+        ];
+        assert_eq!(
+            solve(BOUNDS, &rects, &[min(0, 0), min(300, 0), min(0, 0)]),
+            vec![
+                rect(100, 0, 500, 600),
+                rect(500, 0, 800, 600),
+                rect(800, 0, 1100, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn window_at_the_far_edge_pushes_back() {
+        let rects = [rect(100, 0, 900, 600), rect(900, 0, 1100, 600)];
+        assert_eq!(
+            solve(BOUNDS, &rects, &[min(0, 0), min(400, 0)]),
+            vec![rect(100, 0, 700, 600), rect(700, 0, 1100, 600)]
+        );
+    }
+
+    #[test]
+    fn both_axes_are_solved() {
+        let rects = [
+            rect(100, 0, 600, 600),
+            rect(600, 0, 1100, 100),
+            rect(600, 100, 1100, 600),
+        ];
+        assert_eq!(
+            solve(BOUNDS, &rects, &[min(0, 0), min(700, 250), min(0, 0)]),
+            vec![
+                rect(100, 0, 400, 600),
+                rect(400, 0, 1100, 250),
+                rect(400, 250, 1100, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn infeasible_minimums_stack_windows() {
+        let rects = [rect(100, 0, 600, 600), rect(600, 0, 1100, 600)];
+        assert_eq!(
+            solve(BOUNDS, &rects, &[min(600, 0), min(600, 0)]),
+            vec![BOUNDS, BOUNDS]
+        );
+    }
+}
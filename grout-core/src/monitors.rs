@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Rect;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Monitor {
+    pub area: Rect,
+    pub work_area: Rect,
+}
+
+fn overlap(a: Rect, b: Rect) -> i64 {
+    let width = (a.right.min(b.right) - a.left.max(b.left)).max(0) as i64;
+    let height = (a.bottom.min(b.bottom) - a.top.max(b.top)).max(0) as i64;
+    width * height
+}
+
+fn distance(area: Rect, window: Rect) -> i64 {
+    let (x, y) = (
+        (window.left as i64 + window.right as i64) / 2,
+        (window.top as i64 + window.bottom as i64) / 2,
+    );
+    let dx = (area.left as i64 - x).max(x - area.right as i64).max(0);
+    let dy = (area.top as i64 - y).max(y - area.bottom as i64).max(0);
+    dx * dx + dy * dy
+}
+
+/// Picks the monitor a window at `window` belongs to: the one it overlaps
+/// the most, or when it's off every monitor the one nearest its centre.
+pub fn monitor_for(monitors: &[Monitor], window: Rect) -> Option<usize> {
+    let best = (0..monitors.len()).max_by_key(|&idx| {
+        // Ties go to the first monitor
+        (overlap(monitors[idx].area, window), -(idx as i64))
+    })?;
+    if overlap(monitors[best].area, window) > 0 {
+        return Some(best);
+    }
+    (0..monitors.len()).min_by_key(|&idx| (distance(monitors[idx].area, window), idx))
+}
+
+fn centre(r: Rect) -> (i64, i64) {
+    (
+        (r.left as i64 + r.right as i64) / 2,
+        (r.top as i64 + r.bottom as i64) / 2,
+    )
+}
+
+/// Finds the monitor next to `from` in `direction` by where the monitors
+/// are rather than the order they were enumerated in. Monitors sharing some
+/// rows with `from` win over ones that are only diagonally to that side,
+/// then the nearest one wins.
+pub fn neighbour(monitors: &[Monitor], from: usize, direction: Direction) -> Option<usize> {
+    let origin = monitors.get(from)?.area;
+    let (x, y) = centre(origin);
+    (0..monitors.len())
+        .filter(|&idx| idx != from)
+        .filter(|&idx| {
+            let (other_x, _) = centre(monitors[idx].area);
+            match direction {
+                Direction::Left => other_x < x,
+                Direction::Right => other_x > x,
+            }
+        })
+        .min_by_key(|&idx| {
+            let area = monitors[idx].area;
+            let (other_x, other_y) = centre(area);
+            let shares_rows = area.top < origin.bottom && origin.top < area.bottom;
+            (!shares_rows, (other_x - x).abs(), (other_y - y).abs(), idx)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn monitors(areas: &[Rect]) -> Vec<Monitor> {
+        areas
+            .iter()
+            .map(|&area| Monitor {
+                area,
+                work_area: area,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn window_goes_to_the_monitor_it_overlaps_most() {
+        let monitors = monitors(&[rect(0, 0, 1920, 1080), rect(1920, 0, 3840, 1080)]);
+        assert_eq!(monitor_for(&monitors, rect(100, 100, 800, 600)), Some(0));
+        assert_eq!(monitor_for(&monitors, rect(1800, 100, 2400, 600)), Some(1));
+        assert_eq!(monitor_for(&monitors, rect(1520, 100, 2320, 600)), Some(0));
+    }
+
+    #[test]
+    fn offscreen_window_goes_to_the_nearest_monitor() {
+        let monitors = monitors(&[rect(0, 0, 1920, 1080), rect(1920, -400, 3000, 1520)]);
+        assert_eq!(monitor_for(&monitors, rect(3100, 0, 3500, 300)), Some(1));
+        assert_eq!(
+            monitor_for(&monitors, rect(-800, 1200, -100, 1600)),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn no_monitors_means_no_monitor() {
+        assert_eq!(monitor_for(&[], rect(0, 0, 100, 100)), None);
+    }
+
+    #[test]
+    fn neighbours_follow_geometry_not_enumeration_order() {
+        let monitors = monitors(&[
+            rect(0, 0, 1920, 1080),
+            rect(1920, 0, 3840, 1080),
+            rect(-1920, 0, 0, 1080),
+        ]);
+        assert_eq!(neighbour(&monitors, 0, Direction::Left), Some(2));
+        assert_eq!(neighbour(&monitors, 0, Direction::Right), Some(1));
+        assert_eq!(neighbour(&monitors, 1, Direction::Left), Some(0));
+        assert_eq!(neighbour(&monitors, 2, Direction::Left), None);
+        assert_eq!(neighbour(&monitors, 1, Direction::Right), None);
+    }
+
+    #[test]
+    fn side_by_side_monitor_beats_diagonal_one() {
+        let monitors = monitors(&[
+            rect(0, 0, 1920, 1080),
+            rect(1000, 1080, 2920, 2160),
+            rect(1920, -500, 4480, 940),
+        ]);
+        assert_eq!(neighbour(&monitors, 0, Direction::Right), Some(2));
+        assert_eq!(neighbour(&monitors, 2, Direction::Left), Some(0));
+    }
+
+    #[test]
+    fn unknown_monitor_has_no_neighbours() {
+        let monitors = monitors(&[rect(0, 0, 1920, 1080)]);
+        assert_eq!(neighbour(&monitors, 3, Direction::Left), None);
+    }
+}
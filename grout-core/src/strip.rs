@@ -0,0 +1,182 @@
+use crate::Rect;
+
+const MIN_WIDTH: f32 = 0.1;
+const MAX_WIDTH: f32 = 1.0;
+pub const DEFAULT_WIDTH: f32 = 0.5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Strip<T> {
+    widths: Vec<(T, f32)>,
+    default_width: f32,
+    offset: i32,
+}
+
+impl<T: Copy + PartialEq> Strip<T> {
+    pub fn new(default_width: f32) -> Self {
+        Strip {
+            widths: vec![],
+            default_width: default_width.clamp(MIN_WIDTH, MAX_WIDTH),
+            offset: 0,
+        }
+    }
+
+    pub fn offset(&self) -> i32 {
+        self.offset
+    }
+
+    pub fn width(&self, key: T) -> f32 {
+        self.widths
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map_or(self.default_width, |(_, w)| *w)
+    }
+
+    pub fn adjust_width(&mut self, key: T, delta: f32) {
+        let width = (self.width(key) + delta).clamp(MIN_WIDTH, MAX_WIDTH);
+        match self.widths.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, w)) => *w = width,
+            None => self.widths.push((key, width)),
+        }
+    }
+
+    pub fn forget(&mut self, key: T) {
+        self.widths.retain(|(k, _)| *k != key);
+    }
+
+    fn columns(&self, keys: &[T], viewport: i32) -> Vec<(i32, i32)> {
+        let mut x = 0;
+        keys.iter()
+            .map(|&key| {
+                let width = (viewport as f32 * self.width(key)).round() as i32;
+                let column = (x, width);
+                x += width;
+                column
+            })
+            .collect()
+    }
+
+    pub fn scroll_to(&mut self, keys: &[T], focused: T, viewport: i32) {
+        let columns = self.columns(keys, viewport);
+        let total: i32 = columns.iter().map(|(_, w)| w).sum();
+        if let Some(&(x, width)) = keys
+            .iter()
+            .position(|&k| k == focused)
+            .map(|idx| &columns[idx])
+        {
+            if x < self.offset {
+                self.offset = x;
+            } else if x + width > self.offset + viewport {
+                self.offset = x + width - viewport;
+            }
+        }
+        self.offset = self.offset.clamp(0, (total - viewport).max(0));
+    }
+
+    pub fn rects(&self, keys: &[T], bounds: Rect) -> Vec<Rect> {
+        let viewport = bounds.right - bounds.left;
+        self.columns(keys, viewport)
+            .into_iter()
+            .map(|(x, width)| Rect {
+                left: bounds.left + x - self.offset,
+                top: bounds.top,
+                right: bounds.left + x - self.offset + width,
+                bottom: bounds.bottom,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    const BOUNDS: Rect = Rect {
+        left: 100,
+        top: 0,
+        right: 1100,
+        bottom: 500,
+    };
+
+    #[test]
+    fn columns_continue_past_the_viewport() {
+        let strip = Strip::new(0.5);
+        assert_eq!(
+            strip.rects(&[1, 2, 3], BOUNDS),
+            vec![
+                rect(100, 0, 600, 500),
+                rect(600, 0, 1100, 500),
+                rect(1100, 0, 1600, 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn scroll_to_reveals_column_on_the_right() {
+        let mut strip = Strip::new(0.5);
+        strip.scroll_to(&[1, 2, 3], 3, 1000);
+        assert_eq!(strip.offset(), 500);
+        assert_eq!(
+            strip.rects(&[1, 2, 3], BOUNDS),
+            vec![
+                rect(-400, 0, 100, 500),
+                rect(100, 0, 600, 500),
+                rect(600, 0, 1100, 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn scroll_to_reveals_column_on_the_left() {
+        let mut strip = Strip::new(0.4);
+        strip.scroll_to(&[1, 2, 3, 4], 4, 1000);
+        assert_eq!(strip.offset(), 600);
+        strip.scroll_to(&[1, 2, 3, 4], 2, 1000);
+        assert_eq!(strip.offset(), 400);
+    }
+
+    #[test]
+    fn scroll_to_visible_column_does_not_move() {
+        let mut strip = Strip::new(0.25);
+        strip.scroll_to(&[1, 2, 3, 4, 5, 6], 6, 1000);
+        assert_eq!(strip.offset(), 500);
+        strip.scroll_to(&[1, 2, 3, 4, 5, 6], 4, 1000);
+        assert_eq!(strip.offset(), 500);
+    }
+
+    #[test]
+    fn offset_is_clamped_when_columns_disappear() {
+        let mut strip = Strip::new(0.5);
+        strip.scroll_to(&[1, 2, 3, 4], 4, 1000);
+        assert_eq!(strip.offset(), 1000);
+        strip.scroll_to(&[1, 2], 2, 1000);
+        assert_eq!(strip.offset(), 0);
+    }
+
+    #[test]
+    fn column_widths_are_adjustable() {
+        let mut strip = Strip::new(0.5);
+        strip.adjust_width(2, 0.25);
+        strip.adjust_width(3, -1.0);
+        assert_eq!(strip.width(2), 0.75);
+        assert_eq!(strip.width(3), MIN_WIDTH);
+        assert_eq!(
+            strip.rects(&[1, 2, 3], BOUNDS),
+            vec![
+                rect(100, 0, 600, 500),
+                rect(600, 0, 1350, 500),
+                rect(1350, 0, 1450, 500),
+            ]
+        );
+        strip.forget(2);
+        assert_eq!(strip.width(2), 0.5);
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+
+use crate::Rect;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    Count { expected: usize, actual: usize },
+    Empty(usize),
+    OutOfBounds(usize),
+    Overlap(usize, usize),
+    Uncovered { covered: i64, area: i64 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::Count { expected, actual } => {
+                write!(f, "expected {expected} rects, got {actual}")
+            }
+            Violation::Empty(idx) => write!(f, "rect {idx} has no area"),
+            Violation::OutOfBounds(idx) => write!(f, "rect {idx} is outside the bounds"),
+            Violation::Overlap(a, b) => write!(f, "rects {a} and {b} overlap"),
+            Violation::Uncovered { covered, area } => {
+                write!(f, "rects cover {covered} of {area} pixels")
+            }
+        }
+    }
+}
+
+fn area(r: &Rect) -> i64 {
+    (r.right - r.left) as i64 * (r.bottom - r.top) as i64
+}
+
+fn intersects(a: &Rect, b: &Rect) -> bool {
+    a.left.max(b.left) < a.right.min(b.right) && a.top.max(b.top) < a.bottom.min(b.bottom)
+}
+
+fn union_area(bounds: Rect, rects: &[Rect]) -> i64 {
+    let rects: Vec<Rect> = rects
+        .iter()
+        .map(|r| Rect {
+            left: r.left.max(bounds.left),
+            top: r.top.max(bounds.top),
+            right: r.right.min(bounds.right),
+            bottom: r.bottom.min(bounds.bottom),
+        })
+        .collect();
+    let mut xs: Vec<i32> = rects.iter().flat_map(|r| [r.left, r.right]).collect();
+    let mut ys: Vec<i32> = rects.iter().flat_map(|r| [r.top, r.bottom]).collect();
+    xs.sort_unstable();
+    xs.dedup();
+    ys.sort_unstable();
+    ys.dedup();
+    let mut covered = 0;
+    for x in xs.windows(2) {
+        for y in ys.windows(2) {
+            let cell = Rect {
+                left: x[0],
+                top: y[0],
+                right: x[1],
+                bottom: y[1],
+            };
+            if rects.iter().any(|r| intersects(r, &cell)) {
+                covered += area(&cell);
+            }
+        }
+    }
+    covered
+}
+
+/// Checks that `rects` is a valid arrangement of `n` windows in `bounds`:
+/// one non-empty rect per window, all inside the bounds, covering them
+/// completely and, unless `allow_overlap` is set, not overlapping.
+pub fn check_layout(bounds: Rect, n: usize, rects: &[Rect], allow_overlap: bool) -> Vec<Violation> {
+    let mut violations = vec![];
+    if rects.len() != n {
+        violations.push(Violation::Count {
+            expected: n,
+            actual: rects.len(),
+        });
+    }
+    for (idx, r) in rects.iter().enumerate() {
+        if r.right <= r.left || r.bottom <= r.top {
+            violations.push(Violation::Empty(idx));
+        }
+        if r.left < bounds.left
+            || r.top < bounds.top
+            || r.right > bounds.right
+            || r.bottom > bounds.bottom
+        {
+            violations.push(Violation::OutOfBounds(idx));
+        }
+    }
+    if !allow_overlap {
+        for (a, ra) in rects.iter().enumerate() {
+            for (b, rb) in rects.iter().enumerate().skip(a + 1) {
+                if intersects(ra, rb) {
+                    violations.push(Violation::Overlap(a, b));
+                }
+            }
+        }
+    }
+    if !rects.is_empty() {
+        let covered = union_area(bounds, rects);
+        if covered != area(&bounds) {
+            violations.push(Violation::Uncovered {
+                covered,
+                area: area(&bounds),
+            });
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    const BOUNDS: Rect = Rect {
+        left: 10,
+        top: 10,
+        right: 110,
+        bottom: 60,
+    };
+
+    #[test]
+    fn valid_tiling_passes() {
+        let rects = [rect(10, 10, 60, 60), rect(60, 10, 110, 60)];
+        assert_eq!(check_layout(BOUNDS, 2, &rects, false), vec![]);
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let rects = [
+            rect(0, 10, 60, 60),
+            rect(50, 10, 100, 60),
+            rect(70, 20, 70, 30),
+        ];
+        assert_eq!(
+            check_layout(BOUNDS, 2, &rects, false),
+            vec![
+                Violation::Count {
+                    expected: 2,
+                    actual: 3
+                },
+                Violation::OutOfBounds(0),
+                Violation::Empty(2),
+                Violation::Overlap(0, 1),
+                Violation::Uncovered {
+                    covered: 4500,
+                    area: 5000
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn overlapping_stack_covers_bounds() {
+        let rects = [BOUNDS, BOUNDS];
+        assert_eq!(check_layout(BOUNDS, 2, &rects, true), vec![]);
+        assert_eq!(
+            check_layout(BOUNDS, 2, &rects, false),
+            vec![Violation::Overlap(0, 1)]
+        );
+    }
+
+    #[test]
+    fn gap_at_the_edge_is_uncovered() {
+        let rects = [rect(10, 10, 60, 60), rect(60, 10, 109, 60)];
+        assert_eq!(
+            check_layout(BOUNDS, 2, &rects, false),
+            vec![Violation::Uncovered {
+                covered: 4950,
+                area: 5000
+            }]
+        );
+    }
+}
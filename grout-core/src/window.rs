@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+// Style bits from WinUser.h the rules look at
+const WS_CHILD: u32 = 0x4000_0000;
+const WS_DISABLED: u32 = 0x0800_0000;
+const WS_EX_TOOLWINDOW: u32 = 0x0000_0080;
+const WS_EX_NOACTIVATE: u32 = 0x0800_0000;
+
+/// What the window manager needs to know about a window to decide whether
+/// to manage it and which rules apply to it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct WindowInfo {
+    pub title: String,
+    pub class_name: String,
+    pub process_name: Option<String>,
+    pub style: u32,
+    pub exstyle: u32,
+    pub is_visible: bool,
+    pub is_cloaked: bool,
+    pub has_owner: bool,
+}
+
+impl WindowInfo {
+    /// Reasons not to tile the window, none for application windows that
+    /// show up in Alt+Tab and aren't ignored by the config.
+    pub fn rejections(&self, config: &Config) -> Vec<&'static str> {
+        let WindowInfo {
+            title,
+            class_name,
+            process_name,
+            style,
+            exstyle,
+            is_visible,
+            is_cloaked,
+            has_owner,
+        } = self;
+        let process_name = process_name.as_deref();
+        let ignored = config.ignores(class_name, process_name.unwrap_or_default(), title);
+        [
+            (title.is_empty(), "it has no title"),
+            (process_name.is_none(), "its process is unknown"),
+            (style & WS_DISABLED != 0, "it is disabled"),
+            (ignored, "the config ignores it"),
+            (!is_visible, "it is not visible"),
+            (exstyle & WS_EX_NOACTIVATE != 0, "it can not be activated"),
+            (style & WS_CHILD != 0, "it is a child window"),
+            (exstyle & WS_EX_TOOLWINDOW != 0, "it is a tool window"),
+            (*has_owner, "it has an owner"),
+            (*is_cloaked, "it is cloaked"),
+        ]
+        .into_iter()
+        .filter(|&(rejected, _)| rejected)
+        .map(|(_, reason)| reason)
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_window() -> WindowInfo {
+        WindowInfo {
+            title: "Untitled - Notepad".to_owned(),
+            class_name: "Notepad".to_owned(),
+            process_name: Some("notepad.exe".to_owned()),
+            is_visible: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn application_window_is_tiled() {
+        let config: Config = serde_yaml::from_str("class_names: [WorkerW]").unwrap();
+        assert!(app_window().rejections(&config).is_empty());
+    }
+
+    #[test]
+    fn every_reason_is_listed() {
+        let config: Config = serde_yaml::from_str("process_names: [notepad]").unwrap();
+        let window = WindowInfo {
+            title: String::new(),
+            style: WS_CHILD | WS_DISABLED,
+            exstyle: WS_EX_TOOLWINDOW,
+            is_cloaked: true,
+            ..app_window()
+        };
+        assert_eq!(
+            window.rejections(&config),
+            [
+                "it has no title",
+                "it is disabled",
+                "the config ignores it",
+                "it is a child window",
+                "it is a tool window",
+                "it is cloaked",
+            ]
+        );
+    }
+
+    #[test]
+    fn popups_without_a_process_are_left_alone() {
+        let config: Config = serde_yaml::from_str("layout: Dwindle").unwrap();
+        let window = WindowInfo {
+            process_name: None,
+            exstyle: WS_EX_NOACTIVATE,
+            is_visible: false,
+            has_owner: true,
+            ..app_window()
+        };
+        assert_eq!(
+            window.rejections(&config),
+            [
+                "its process is unknown",
+                "it is not visible",
+                "it can not be activated",
+                "it has an owner",
+            ]
+        );
+    }
+}
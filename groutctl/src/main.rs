@@ -0,0 +1,370 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+use grout_core::{
+    ipc::{self, Explanation, Request, Response, State},
+    Rect, Result,
+};
+
+const EXIT_REFUSED: u8 = 1;
+// 2 is what clap exits with on usage errors
+const EXIT_UNREACHABLE: u8 = 3;
+const EXIT_PROTOCOL: u8 = 4;
+
+/// Controls the running grout-wm
+#[derive(Parser)]
+#[command(
+    version,
+    about,
+    after_help = "Exits with 0 on success, 1 when grout-wm refuses the request, \
+                  2 on usage errors, 3 when grout-wm can not be reached and 4 when \
+                  talking to it or printing its answer fails."
+)]
+struct Cli {
+    /// Print grout-wm's response as JSON
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Switch the active monitor to a layout, or show the layouts in use
+    Layout { name: Option<String> },
+    /// List the windows on screen
+    Windows,
+    /// Reload the config
+    Reload,
+    /// Tell why a window is or isn't tiled
+    Explain {
+        /// Window handle, in decimal or 0x-prefixed hex
+        #[arg(value_parser = parse_hwnd)]
+        hwnd: isize,
+    },
+}
+
+fn parse_hwnd(s: &str) -> std::result::Result<isize, String> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => isize::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.map_err(|e| format!("'{s}' is not a window handle: {e}"))
+}
+
+impl Command {
+    fn request(&self) -> Request {
+        match self {
+            Command::Layout { name: Some(name) } => Request::SetLayout {
+                layout: name.clone(),
+            },
+            Command::Layout { name: None } | Command::Windows => Request::QueryState,
+            Command::Reload => Request::ReloadConfig,
+            Command::Explain { hwnd } => Request::Explain { hwnd: *hwnd },
+        }
+    }
+}
+
+/// Sends the request and waits for the response, the same line-based
+/// encoding the server speaks.
+fn exchange(stream: impl Read + Write, request: &Request) -> Result<Response> {
+    let mut reader = BufReader::new(stream);
+    reader
+        .get_mut()
+        .write_all(ipc::encode(request)?.as_bytes())?;
+    reader.get_mut().flush()?;
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err("grout-wm hung up without answering".into());
+    }
+    ipc::decode(&line)
+}
+
+fn format_rect(rect: Rect) -> String {
+    format!(
+        "{}x{}+{}+{}",
+        rect.width(),
+        rect.height(),
+        rect.left,
+        rect.top
+    )
+}
+
+fn print_layouts(state: &State, out: &mut impl Write) -> io::Result<()> {
+    for (idx, monitor) in state.monitors.iter().enumerate() {
+        writeln!(out, "monitor {idx}: {}", monitor.layout)?;
+    }
+    writeln!(out, "available: {}", state.layouts.join(", "))
+}
+
+fn print_windows(state: &State, out: &mut impl Write) -> io::Result<()> {
+    for (idx, monitor) in state.monitors.iter().enumerate() {
+        writeln!(out, "monitor {idx} ({})", monitor.layout)?;
+        for window in &monitor.windows {
+            let focus = if state.focused == Some(window.hwnd) {
+                "*"
+            } else {
+                " "
+            };
+            let floating = if window.floating { " floating" } else { "" };
+            writeln!(
+                out,
+                "{focus} {:#x} {:?} {} {}{floating}",
+                window.hwnd,
+                window.title,
+                window.process_name.as_deref().unwrap_or("?"),
+                format_rect(window.rect)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn print_explanation(explanation: &Explanation, out: &mut impl Write) -> io::Result<()> {
+    let Explanation {
+        hwnd,
+        title,
+        class_name,
+        process_name,
+        managed,
+        floating,
+        minimized,
+        on_current_desktop,
+        monitor,
+        rejections,
+    } = explanation;
+    writeln!(out, "{hwnd:#x} {title:?}")?;
+    writeln!(
+        out,
+        "  class {class_name:?}, process {}",
+        process_name.as_deref().unwrap_or("unknown")
+    )?;
+    if !managed {
+        writeln!(out, "  not managed because")?;
+        for reason in rejections {
+            writeln!(out, "    {reason}")?;
+        }
+        return Ok(());
+    }
+    match monitor {
+        Some(monitor) => writeln!(out, "  tiled on monitor {monitor}"),
+        None if *floating => writeln!(out, "  managed, floating"),
+        None if *minimized => writeln!(out, "  managed, minimized"),
+        None if !on_current_desktop => writeln!(out, "  managed, on another virtual desktop"),
+        None => writeln!(out, "  managed, not on screen"),
+    }
+}
+
+fn print(cli: &Cli, response: &Response, out: &mut impl Write) -> Result<()> {
+    if cli.json {
+        out.write_all(ipc::encode(response)?.as_bytes())?;
+        return Ok(());
+    }
+    match (&cli.command, response) {
+        (Command::Layout { name: None }, Response::State(state)) => print_layouts(state, out)?,
+        (Command::Windows, Response::State(state)) => print_windows(state, out)?,
+        (Command::Explain { .. }, Response::Explanation(explanation)) => {
+            print_explanation(explanation, out)?
+        }
+        (Command::Reload, Response::Ok) => writeln!(out, "config reloaded")?,
+        (_, Response::Ok) => {}
+        (_, response) => return Err(format!("Unexpected response {response:?}").into()),
+    }
+    Ok(())
+}
+
+/// Carries out the command over `stream` and returns the exit code.
+fn run(cli: &Cli, stream: impl Read + Write, out: &mut impl Write, err: &mut impl Write) -> u8 {
+    let response = match exchange(stream, &cli.command.request()) {
+        Ok(response) => response,
+        Err(e) => {
+            let _ = writeln!(err, "groutctl: {e}");
+            return EXIT_PROTOCOL;
+        }
+    };
+    if let Response::Error { message } = &response {
+        let _ = writeln!(err, "groutctl: {message}");
+        return EXIT_REFUSED;
+    }
+    match print(cli, &response, out) {
+        Ok(()) => 0,
+        Err(e) => {
+            let _ = writeln!(err, "groutctl: {e}");
+            EXIT_PROTOCOL
+        }
+    }
+}
+
+#[cfg(windows)]
+fn connect() -> io::Result<impl Read + Write> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(ipc::PIPE_NAME)
+}
+
+#[cfg(not(windows))]
+fn connect() -> io::Result<impl Read + Write> {
+    std::os::unix::net::UnixStream::connect(ipc::socket_path())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let code = match connect() {
+        Ok(stream) => run(&cli, stream, &mut io::stdout(), &mut io::stderr()),
+        Err(e) => {
+            eprintln!("groutctl: Can not reach grout-wm: {e}");
+            EXIT_UNREACHABLE
+        }
+    };
+    ExitCode::from(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread::{self, JoinHandle},
+    };
+
+    use super::*;
+    use grout_core::ipc::{MonitorState, WindowState};
+
+    /// Answers one request with `response`, handing back what was asked.
+    fn mock_server(response: Option<Response>) -> (TcpStream, JoinHandle<Request>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some(response) = response {
+                let encoded = ipc::encode(&response).unwrap();
+                reader.get_mut().write_all(encoded.as_bytes()).unwrap();
+            }
+            ipc::decode(&line).unwrap()
+        });
+        (client, server)
+    }
+
+    fn invoke(args: &[&str], response: Option<Response>) -> (Request, u8, String, String) {
+        let cli = Cli::try_parse_from(["groutctl"].iter().chain(args)).unwrap();
+        let (client, server) = mock_server(response);
+        let (mut out, mut err) = (vec![], vec![]);
+        let code = run(&cli, client, &mut out, &mut err);
+        (
+            server.join().unwrap(),
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    fn state() -> State {
+        let rect = Rect {
+            left: 0,
+            top: 0,
+            right: 500,
+            bottom: 600,
+        };
+        State {
+            layouts: vec!["Dwindle".to_owned(), "Columns".to_owned()],
+            focused: Some(0x1a),
+            monitors: vec![MonitorState {
+                layout: "Dwindle".to_owned(),
+                work_area: rect,
+                windows: vec![WindowState {
+                    hwnd: 0x1a,
+                    title: "Notes".to_owned(),
+                    class_name: "Notepad".to_owned(),
+                    process_name: Some("notepad.exe".to_owned()),
+                    rect,
+                    floating: false,
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn layout_is_set_by_name() {
+        let (request, code, out, _) = invoke(&["layout", "columns"], Some(Response::Ok));
+        assert_eq!(
+            request,
+            Request::SetLayout {
+                layout: "columns".to_owned()
+            }
+        );
+        assert_eq!(code, 0);
+        assert_eq!(out, "");
+    }
+
+    #[test]
+    fn windows_are_listed_for_people_and_as_json() {
+        let (request, code, out, _) = invoke(&["windows"], Some(Response::State(state())));
+        assert_eq!(request, Request::QueryState);
+        assert_eq!(code, 0);
+        assert_eq!(
+            out,
+            "monitor 0 (Dwindle)\n* 0x1a \"Notes\" notepad.exe 500x600+0+0\n"
+        );
+        let (_, code, out, _) = invoke(&["windows", "--json"], Some(Response::State(state())));
+        assert_eq!(code, 0);
+        assert_eq!(
+            ipc::decode::<Response>(&out).unwrap(),
+            Response::State(state())
+        );
+    }
+
+    #[test]
+    fn refused_request_exits_with_the_reason() {
+        let refusal = Response::error("Could not parse user config file");
+        let (request, code, out, err) = invoke(&["reload"], Some(refusal));
+        assert_eq!(request, Request::ReloadConfig);
+        assert_eq!(code, EXIT_REFUSED);
+        assert_eq!(out, "");
+        assert_eq!(err, "groutctl: Could not parse user config file\n");
+    }
+
+    #[test]
+    fn explain_takes_hex_handles() {
+        let explanation = Explanation {
+            hwnd: 0x1a,
+            title: String::new(),
+            class_name: "Tool".to_owned(),
+            process_name: None,
+            managed: false,
+            floating: false,
+            minimized: false,
+            on_current_desktop: true,
+            monitor: None,
+            rejections: vec!["it has no title".to_owned()],
+        };
+        let (request, code, out, _) = invoke(
+            &["explain", "0x1a"],
+            Some(Response::Explanation(explanation)),
+        );
+        assert_eq!(request, Request::Explain { hwnd: 0x1a });
+        assert_eq!(code, 0);
+        assert!(out.ends_with("not managed because\n    it has no title\n"));
+        assert!(Cli::try_parse_from(["groutctl", "explain", "notepad"]).is_err());
+    }
+
+    #[test]
+    fn server_hanging_up_is_a_protocol_error() {
+        let (_, code, _, err) = invoke(&["reload"], None);
+        assert_eq!(code, EXIT_PROTOCOL);
+        assert!(err.starts_with("groutctl: grout-wm hung up"));
+    }
+
+    #[test]
+    fn unexpected_response_is_a_protocol_error() {
+        let (_, code, out, err) = invoke(&["explain", "0x1a"], Some(Response::State(state())));
+        assert_eq!(code, EXIT_PROTOCOL);
+        assert_eq!(out, "");
+        assert!(err.starts_with("groutctl: Unexpected response State"));
+    }
+}
@@ -5,7 +5,7 @@ use std::{
 
 use log::{debug, error, info};
 use windows::{
-    core::w,
+    core::{w, HSTRING, PCWSTR},
     Win32::{
         Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM},
         Graphics::{
@@ -26,6 +26,7 @@ use windows::{
     },
 };
 
+use grout_core::layout::LayoutRegistry;
 use grout_wm::{to_wide_arr, Result, HIWORD, LOWORD};
 
 use crate::{
@@ -35,13 +36,15 @@ use crate::{
         set_win_event_hook, set_window_long_ptr, show_window,
     },
     windowmanager::{
-        WindowManager, MSG_CLOAKED, MSG_MINIMIZEEND, MSG_MINIMIZESTART, MSG_MOVESIZEEND,
-        MSG_UNCLOAKED, SHELL_HOOK_ID,
+        WindowManager, CMD_GROW_MAIN_RATIO, CMD_LAYOUT_BASE, CMD_SHRINK_MAIN_RATIO, MSG_CLOAKED,
+        MSG_MINIMIZEEND, MSG_MINIMIZESTART, MSG_MOVESIZEEND, MSG_UNCLOAKED, SHELL_HOOK_ID,
     },
 };
 
 static MY_HWND: OnceLock<HWND> = OnceLock::new();
 
+const MAX_THUMB_BUTTONS: usize = 7;
+
 pub struct AppWindow {
     hwnd: HWND,
     cloaked_event_hook: HWINEVENTHOOK,
@@ -95,6 +98,10 @@ impl AppWindow {
         })
     }
 
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+
     pub fn show_window(self) -> Result<Self> {
         show_window(self.hwnd);
         Ok(Self {
@@ -136,66 +143,51 @@ impl AppWindow {
         })
     }
 
-    pub fn set_thumb_buttons(self) -> Result<Self> {
+    pub fn set_thumb_buttons(self, layouts: &LayoutRegistry) -> Result<Self> {
         let is_light_theme = win32::theme::is_light_theme();
         let instance = get_module_handle()?;
         let dw_mask = windows::Win32::UI::Shell::THB_ICON
             | windows::Win32::UI::Shell::THB_TOOLTIP
             | windows::Win32::UI::Shell::THB_FLAGS;
-        let dwindle_icon = if is_light_theme {
-            w!("dwindle-blk")
-        } else {
-            w!("dwindle-wht")
-        };
-        let monocle_icon = if is_light_theme {
-            w!("monocle-blk")
-        } else {
-            w!("monocle-wht")
-        };
-        let columns_icon = if is_light_theme {
-            w!("columns-blk")
-        } else {
-            w!("columns-wht")
-        };
-        let focus_icon = if is_light_theme {
-            w!("focus-blk")
-        } else {
-            w!("focus-wht")
+        let icon = |name: &str| {
+            let variant = if is_light_theme { "blk" } else { "wht" };
+            HSTRING::from(format!("{name}-{variant}"))
         };
-        let buttons: Vec<windows::Win32::UI::Shell::THUMBBUTTON> = vec![
-            windows::Win32::UI::Shell::THUMBBUTTON {
-                dwMask: dw_mask,
-                iId: 0,
-                hIcon: load_icon(instance, dwindle_icon).unwrap(),
-                dwFlags: windows::Win32::UI::Shell::THBF_DISMISSONCLICK,
-                szTip: to_wide_arr!("Dwindle layout"),
-                ..Default::default()
-            },
-            windows::Win32::UI::Shell::THUMBBUTTON {
-                dwMask: dw_mask,
-                iId: 1,
-                hIcon: load_icon(instance, monocle_icon).unwrap(),
-                dwFlags: windows::Win32::UI::Shell::THBF_DISMISSONCLICK,
-                szTip: to_wide_arr!("Monocle layout"),
-                ..Default::default()
-            },
-            windows::Win32::UI::Shell::THUMBBUTTON {
-                dwMask: dw_mask,
-                iId: 2,
-                hIcon: load_icon(instance, columns_icon).unwrap(),
-                dwFlags: windows::Win32::UI::Shell::THBF_DISMISSONCLICK,
-                szTip: to_wide_arr!("Columns layout"),
-                ..Default::default()
-            },
-            windows::Win32::UI::Shell::THUMBBUTTON {
+        let ratio_buttons = [
+            (
+                CMD_GROW_MAIN_RATIO,
+                icon("grow"),
+                "Grow main area".to_owned(),
+            ),
+            (
+                CMD_SHRINK_MAIN_RATIO,
+                icon("shrink"),
+                "Shrink main area".to_owned(),
+            ),
+        ];
+        let layout_buttons: Vec<_> = layouts
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, layout)| {
+                let id = CMD_LAYOUT_BASE + idx as u32;
+                layout
+                    .icon()
+                    .map(|name| (id, icon(name), format!("{} layout", layout.name())))
+            })
+            .take(MAX_THUMB_BUTTONS - ratio_buttons.len())
+            .collect();
+        let buttons: Vec<windows::Win32::UI::Shell::THUMBBUTTON> = layout_buttons
+            .iter()
+            .chain(ratio_buttons.iter())
+            .map(|(id, icon, tip)| windows::Win32::UI::Shell::THUMBBUTTON {
                 dwMask: dw_mask,
-                iId: 3,
-                hIcon: load_icon(instance, focus_icon).unwrap(),
+                iId: *id,
+                hIcon: load_icon(instance, PCWSTR(icon.as_ptr())).unwrap(),
                 dwFlags: windows::Win32::UI::Shell::THBF_DISMISSONCLICK,
-                szTip: to_wide_arr!("Focus layout"),
+                szTip: to_wide_arr!(tip),
                 ..Default::default()
-            },
-        ];
+            })
+            .collect();
         let taskbarlist = win32::taskbar::TaskbarList::new()?;
         taskbarlist.thumb_bar_add_buttons(self.hwnd, &buttons)?;
         Ok(Self {
@@ -297,8 +289,8 @@ impl AppWindow {
             WM_QUERYOPEN => LRESULT(0),
             _ => {
                 let wm = get_window_long_ptr(hwnd, GWLP_USERDATA) as *mut WindowManager;
-                if !wm.is_null() {
-                    return unsafe { (*wm).message_loop(hwnd, msg, wparam, lparam) };
+                if !wm.is_null() && unsafe { (*wm).message_loop(msg, wparam, lparam) } {
+                    return LRESULT(0);
                 }
                 def_window_proc(hwnd, msg, wparam, lparam)
             }
@@ -0,0 +1,191 @@
+use std::{cell::RefCell, rc::Rc};
+
+use windows::Win32::Foundation::HWND;
+
+use super::Backend;
+use grout_core::{
+    desktops::{DesktopId, DesktopProvider},
+    minsize::MinSize,
+    monitors::Monitor,
+    window::WindowInfo,
+    Point, Rect,
+};
+use grout_wm::Result;
+
+#[derive(Clone, Debug)]
+pub struct FakeWindow {
+    pub hwnd: HWND,
+    pub info: WindowInfo,
+    pub rect: Rect,
+    pub min_size: MinSize,
+    pub is_iconic: bool,
+    pub desktop: DesktopId,
+}
+
+#[derive(Default)]
+struct State {
+    windows: Vec<FakeWindow>,
+    monitors: Vec<Monitor>,
+    desktop: DesktopId,
+    foreground: HWND,
+    cursor: Point,
+}
+
+/// In-memory stand-in for the desktop. Clones share the same state so a
+/// test can keep a handle after giving one to the window manager.
+#[derive(Clone, Default)]
+pub struct FakeBackend(Rc<RefCell<State>>);
+
+impl FakeBackend {
+    #[cfg(test)]
+    pub fn new(monitors: Vec<Monitor>) -> Self {
+        let backend = FakeBackend::default();
+        backend.0.borrow_mut().monitors = monitors;
+        backend
+    }
+
+    /// Opens an ordinary application window on the current desktop.
+    #[cfg(test)]
+    pub fn open(&self, hwnd: isize, title: &str, rect: Rect) -> HWND {
+        let mut state = self.0.borrow_mut();
+        let window = FakeWindow {
+            hwnd: HWND(hwnd),
+            info: WindowInfo {
+                title: title.to_owned(),
+                class_name: "Fake".to_owned(),
+                process_name: Some(format!("{title}.exe")),
+                is_visible: true,
+                ..Default::default()
+            },
+            rect,
+            min_size: MinSize::default(),
+            is_iconic: false,
+            desktop: state.desktop,
+        };
+        state.windows.push(window);
+        HWND(hwnd)
+    }
+
+    /// Adds the window, or replaces the one with the same handle.
+    pub fn put(&self, window: FakeWindow) {
+        let mut state = self.0.borrow_mut();
+        match state.windows.iter_mut().find(|w| w.hwnd == window.hwnd) {
+            Some(existing) => *existing = window,
+            None => state.windows.push(window),
+        }
+    }
+
+    pub fn close(&self, hwnd: HWND) {
+        self.0.borrow_mut().windows.retain(|w| w.hwnd != hwnd);
+    }
+
+    pub fn update(&self, hwnd: HWND, f: impl FnOnce(&mut FakeWindow)) {
+        if let Some(window) = self
+            .0
+            .borrow_mut()
+            .windows
+            .iter_mut()
+            .find(|w| w.hwnd == hwnd)
+        {
+            f(window);
+        }
+    }
+
+    pub fn rect(&self, hwnd: HWND) -> Rect {
+        self.window_rect(hwnd)
+    }
+
+    pub fn switch_desktop(&self, desktop: DesktopId) {
+        self.0.borrow_mut().desktop = desktop;
+    }
+
+    pub fn set_monitors(&self, monitors: Vec<Monitor>) {
+        self.0.borrow_mut().monitors = monitors;
+    }
+
+    pub fn set_cursor_pos(&self, cursor: Point) {
+        self.0.borrow_mut().cursor = cursor;
+    }
+
+    fn with_window<T: Default>(&self, hwnd: HWND, f: impl FnOnce(&FakeWindow) -> T) -> T {
+        self.0
+            .borrow()
+            .windows
+            .iter()
+            .find(|w| w.hwnd == hwnd)
+            .map(f)
+            .unwrap_or_default()
+    }
+}
+
+impl DesktopProvider<HWND> for FakeBackend {
+    fn desktop_of(&self, hwnd: HWND) -> Option<DesktopId> {
+        self.with_window(hwnd, |w| Some(w.desktop))
+    }
+
+    fn is_on_current_desktop(&self, hwnd: HWND) -> bool {
+        let desktop = self.0.borrow().desktop;
+        self.with_window(hwnd, |w| w.desktop == desktop)
+    }
+
+    fn current_desktop(&self) -> Option<DesktopId> {
+        Some(self.0.borrow().desktop)
+    }
+}
+
+impl Backend for FakeBackend {
+    fn windows(&self) -> Vec<HWND> {
+        self.0.borrow().windows.iter().map(|w| w.hwnd).collect()
+    }
+
+    fn window_info(&self, hwnd: HWND) -> WindowInfo {
+        self.with_window(hwnd, |w| w.info.clone())
+    }
+
+    fn is_iconic(&self, hwnd: HWND) -> bool {
+        self.with_window(hwnd, |w| w.is_iconic)
+    }
+
+    fn window_rect(&self, hwnd: HWND) -> Rect {
+        self.with_window(hwnd, |w| w.rect)
+    }
+
+    fn visible_frame(&self, hwnd: HWND) -> Rect {
+        self.window_rect(hwnd)
+    }
+
+    // Like real windows, fake ones don't shrink below their minimum size
+    fn set_window_positions(&self, positions: &[(HWND, Rect)]) {
+        for &(hwnd, rect) in positions {
+            self.update(hwnd, |w| {
+                w.rect = Rect {
+                    right: rect.right.max(rect.left + w.min_size.width),
+                    bottom: rect.bottom.max(rect.top + w.min_size.height),
+                    ..rect
+                }
+            });
+        }
+    }
+
+    fn foreground_window(&self) -> HWND {
+        self.0.borrow().foreground
+    }
+
+    fn set_foreground_window(&self, hwnd: HWND) -> bool {
+        let exists = self.with_window(hwnd, |_| true);
+        if exists {
+            self.0.borrow_mut().foreground = hwnd;
+        }
+        exists
+    }
+
+    fn cursor_pos(&self) -> Point {
+        self.0.borrow().cursor
+    }
+
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        Ok(self.0.borrow().monitors.clone())
+    }
+
+    fn invalidate_thumbnail(&self, _hwnd: HWND) {}
+}
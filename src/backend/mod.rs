@@ -0,0 +1,25 @@
+use windows::Win32::Foundation::HWND;
+
+use grout_core::{desktops::DesktopProvider, monitors::Monitor, window::WindowInfo, Point, Rect};
+use grout_wm::Result;
+
+pub mod fake;
+
+/// Everything `WindowManager` asks of the platform: window queries,
+/// positioning, virtual desktop membership, monitors and the cursor.
+pub trait Backend: DesktopProvider<HWND> {
+    /// Top-level windows in z-order.
+    fn windows(&self) -> Vec<HWND>;
+    fn window_info(&self, hwnd: HWND) -> WindowInfo;
+    fn is_iconic(&self, hwnd: HWND) -> bool;
+    fn window_rect(&self, hwnd: HWND) -> Rect;
+    /// Frame the user sees, without the invisible resize borders.
+    fn visible_frame(&self, hwnd: HWND) -> Rect;
+    fn set_window_positions(&self, positions: &[(HWND, Rect)]);
+    fn foreground_window(&self) -> HWND;
+    fn set_foreground_window(&self, hwnd: HWND) -> bool;
+    fn cursor_pos(&self) -> Point;
+    fn monitors(&self) -> Result<Vec<Monitor>>;
+    /// Asks for a fresh taskbar thumbnail of the app window.
+    fn invalidate_thumbnail(&self, hwnd: HWND);
+}
@@ -0,0 +1,209 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread,
+};
+
+use log::error;
+
+#[cfg(windows)]
+use crate::win32::pipe::PipeConnection;
+use grout_core::ipc::{self, Request, Response};
+
+/// Longest request line a client may send, requests are a few dozen bytes.
+const MAX_REQUEST_LEN: usize = 64 * 1024;
+
+/// A request on its way to the window manager, with where to send the
+/// response.
+pub type Envelope = (Request, Sender<Response>);
+
+/// Gets the window manager to look at its pending requests.
+pub type Wake = Arc<dyn Fn() + Send + Sync>;
+
+fn dispatch(request: Request, requests: &Sender<Envelope>, wake: &Wake) -> Response {
+    let (reply, response) = mpsc::channel();
+    if requests.send((request, reply)).is_err() {
+        return Response::error("Window manager is not running");
+    }
+    wake();
+    response
+        .recv()
+        .unwrap_or_else(|_| Response::error("Window manager did not answer"))
+}
+
+/// Answers a client's requests, one per line, until it hangs up or sends a
+/// line longer than `MAX_REQUEST_LEN`.
+fn serve(stream: impl Read + Write, requests: Sender<Envelope>, wake: Wake) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = vec![];
+    loop {
+        line.clear();
+        let limit = MAX_REQUEST_LEN as u64 + 1;
+        if reader.by_ref().take(limit).read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        let too_long = line.len() > MAX_REQUEST_LEN && !line.ends_with(b"\n");
+        let response = match too_long {
+            true => Response::error(format!("Request is longer than {MAX_REQUEST_LEN} bytes")),
+            false => match ipc::decode::<Request>(&String::from_utf8_lossy(&line)) {
+                Ok(request) => dispatch(request, &requests, &wake),
+                Err(e) => Response::error(e.to_string()),
+            },
+        };
+        let encoded = ipc::encode(&response).map_err(io::Error::other)?;
+        reader.get_mut().write_all(encoded.as_bytes())?;
+        reader.get_mut().flush()?;
+        // The rest of the line can't be told apart from the next request
+        if too_long {
+            return Ok(());
+        }
+    }
+}
+
+fn spawn_client(
+    stream: impl Read + Write + Send + 'static,
+    requests: &Sender<Envelope>,
+    wake: &Wake,
+) {
+    let (requests, wake) = (requests.clone(), wake.clone());
+    thread::spawn(move || {
+        if let Err(e) = serve(stream, requests, wake) {
+            error!("Lost IPC client: {e}");
+        }
+    });
+}
+
+/// Listens on the named pipe, each client getting a thread of its own.
+#[cfg(windows)]
+pub fn spawn_pipe_server(requests: Sender<Envelope>, wake: Wake) {
+    thread::spawn(move || loop {
+        match PipeConnection::accept(ipc::PIPE_NAME) {
+            Ok(connection) => spawn_client(connection, &requests, &wake),
+            Err(e) => {
+                error!("Can not listen on {}: {e}", ipc::PIPE_NAME);
+                return;
+            }
+        }
+    });
+}
+
+/// Listens on a Unix domain socket, each client getting a thread of its own.
+#[cfg(unix)]
+// The window manager only runs on Windows, so far only the tests listen here
+#[allow(dead_code)]
+pub fn spawn_socket_server(
+    path: &std::path::Path,
+    requests: Sender<Envelope>,
+    wake: Wake,
+) -> io::Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    // Left behind by an earlier run
+    let _ = std::fs::remove_file(path);
+    let listener = UnixListener::bind(path)?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => spawn_client(stream, &requests, &wake),
+                Err(e) => error!("Can not accept IPC client: {e}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{env, os::unix::net::UnixStream, process};
+
+    use windows::Win32::Foundation::{LPARAM, WPARAM};
+
+    use super::*;
+    use crate::{
+        backend::fake::FakeBackend,
+        windowmanager::{WindowManager, MSG_REQUEST},
+    };
+    use grout_core::{monitors::Monitor, Rect};
+
+    #[test]
+    fn socket_clients_are_answered_by_the_window_manager() {
+        let area = Rect {
+            left: 0,
+            top: 0,
+            right: 1000,
+            bottom: 600,
+        };
+        let backend = FakeBackend::new(vec![Monitor {
+            area,
+            work_area: area,
+        }]);
+        backend.open(1, "a", area);
+        backend.open(2, "b", area);
+        let config = serde_yaml::from_str(include_str!("../default.yaml")).unwrap();
+        let mut wm = WindowManager::new(config, vec![], Box::new(backend.clone())).unwrap();
+        wm.enum_windows();
+        let (requests, pending) = mpsc::channel();
+        wm.set_requests(pending);
+        let (woken, wakeups) = mpsc::channel();
+        let path = env::temp_dir().join(format!("grout-wm-test-{}.sock", process::id()));
+        spawn_socket_server(
+            &path,
+            requests,
+            Arc::new(move || {
+                let _ = woken.send(());
+            }),
+        )
+        .unwrap();
+
+        let client_path = path.clone();
+        let client = thread::spawn(move || {
+            let mut stream = UnixStream::connect(client_path).unwrap();
+            stream
+                .write_all(
+                    b"nonsense\n\
+                      {\"command\":\"set-layout\",\"layout\":\"Monocle\"}\n\
+                      {\"command\":\"query-state\"}\n",
+                )
+                .unwrap();
+            BufReader::new(stream)
+                .lines()
+                .take(3)
+                .map(|line| ipc::decode::<Response>(&line.unwrap()).unwrap())
+                .collect::<Vec<_>>()
+        });
+        for _ in 0..2 {
+            wakeups.recv().unwrap();
+            assert!(wm.message_loop(MSG_REQUEST, WPARAM(0), LPARAM(0)));
+        }
+        let responses = client.join().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        assert!(matches!(responses[0], Response::Error { .. }));
+        assert_eq!(responses[1], Response::Ok);
+        let Response::State(state) = &responses[2] else {
+            panic!("Expected the state, got {:?}", responses[2]);
+        };
+        assert_eq!(state.monitors[0].layout, "Monocle");
+        assert_eq!(state.monitors[0].windows.len(), 2);
+    }
+
+    #[test]
+    fn overlong_requests_are_refused() {
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let (requests, _pending) = mpsc::channel();
+        let server = thread::spawn(move || serve(server, requests, Arc::new(|| {})));
+        let request = vec![b'x'; MAX_REQUEST_LEN + 1];
+        client.write_all(&request).unwrap();
+        let mut response = String::new();
+        BufReader::new(&client).read_line(&mut response).unwrap();
+        assert_eq!(
+            ipc::decode::<Response>(&response).unwrap(),
+            Response::error(format!("Request is longer than {MAX_REQUEST_LEN} bytes"))
+        );
+        // Served without waiting for the end of the line
+        server.join().unwrap().unwrap();
+    }
+}
@@ -65,6 +65,12 @@ impl std::convert::From<&str> for Error {
     }
 }
 
+impl std::convert::From<String> for Error {
+    fn from(message: String) -> Self {
+        Error { message }
+    }
+}
+
 impl std::convert::From<windows::core::Error> for Error {
     fn from(err: windows::core::Error) -> Self {
         Error {
@@ -73,6 +79,14 @@ impl std::convert::From<windows::core::Error> for Error {
     }
 }
 
+impl std::convert::From<grout_core::Error> for Error {
+    fn from(err: grout_core::Error) -> Self {
+        Error {
+            message: err.to_string(),
+        }
+    }
+}
+
 impl std::convert::From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Error {
@@ -1,22 +1,64 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+#[cfg(windows)]
 use log::{error, info, LevelFilter};
-use std::env;
+use std::{env, fs::File, io::BufReader};
+#[cfg(windows)]
+use std::{
+    io::BufWriter,
+    sync::{mpsc, Arc},
+};
+#[cfg(windows)]
+use windows::Win32::Foundation::{LPARAM, WPARAM};
 
 use grout_wm::Result;
 
+#[cfg(windows)]
 mod appwindow;
+mod backend;
 mod config;
-mod layout;
+mod ipc;
+mod script;
+mod trace;
+#[cfg(windows)]
 mod win32;
-mod window;
 mod windowmanager;
 
-use crate::appwindow::AppWindow;
-use crate::config::Config;
-use crate::windowmanager::WindowManager;
+#[cfg(windows)]
+use crate::{
+    appwindow::AppWindow,
+    trace::Recorder,
+    win32::backend::Win32Backend,
+    windowmanager::{WindowManager, MSG_REQUEST},
+};
 
+/// Value following `name` on the command line.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = env::args().skip(1);
+    args.find(|arg| arg == name)?;
+    args.next()
+}
+
+fn replay(path: &str) -> Result<()> {
+    for step in trace::replay(BufReader::new(File::open(path)?))? {
+        print!("{step}");
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn main() -> Result<()> {
+    match arg_value("--replay") {
+        Some(path) => replay(&path),
+        None => Err("grout-wm only runs on Windows, apart from --replay <trace>".into()),
+    }
+}
+
+#[cfg(windows)]
 fn main() -> Result<()> {
+    if let Some(path) = arg_value("--replay") {
+        return replay(&path);
+    }
     let mutex_handle = win32::get_mutex().unwrap_or_else(|_e| {
         error!("Can't run multiple instances");
         std::process::exit(1);
@@ -34,13 +76,28 @@ fn main() -> Result<()> {
         error!("Can not initialize com");
         std::process::exit(1);
     });
-    let config = Config::load_default()?.load_or_create_user_config()?;
-    let mut binding = WindowManager::new(config)?;
-    let wm = binding.enum_windows()?;
-    let _appwindow = AppWindow::new_window(wm)?
+    let config = config::load()?;
+    let scripts = config::load_scripts();
+    let mut binding = WindowManager::new(config, scripts, Box::new(Win32Backend::new()?))?;
+    if let Some(path) = arg_value("--record") {
+        info!("Recording events to {path}");
+        binding.record_to(Recorder::new(BufWriter::new(File::create(path)?)));
+    }
+    let (requests, pending) = mpsc::channel();
+    binding.set_requests(pending);
+    let wm = binding.enum_windows();
+    let appwindow = AppWindow::new_window(wm)?;
+    let hwnd = appwindow.hwnd();
+    ipc::spawn_pipe_server(
+        requests,
+        Arc::new(move || {
+            let _ = win32::post_message(hwnd, MSG_REQUEST, WPARAM(0), LPARAM(0));
+        }),
+    );
+    let _appwindow = appwindow
         .show_window()?
         .register_hooks()?
-        .set_thumb_buttons()?
+        .set_thumb_buttons(wm.layouts())?
         .handle_messages()?
         .cleanup();
     info!("quitting");
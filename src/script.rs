@@ -0,0 +1,280 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use log::{error, info};
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use serde::{Deserialize, Serialize};
+
+use grout_core::{
+    layout::{dwindle, LayoutEngine, LayoutParams},
+    Rect,
+};
+use grout_wm::Result;
+
+// Plenty for laying out windows, small enough that a runaway script fails
+// instead of taking the window manager down with it
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_ARRAY_SIZE: usize = 10_000;
+const MAX_STRING_SIZE: usize = 10_000;
+const MAX_MAP_SIZE: usize = 1_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_FUNCTION_EXPR_DEPTH: usize = 32;
+
+/// A layout script as read from the config directory, kept around so traces
+/// can take it along.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ScriptSource {
+    pub name: String,
+    pub source: String,
+}
+
+impl ScriptSource {
+    pub fn read(path: &Path) -> Result<Self> {
+        info!("Reading layout script from {:?}", path);
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let source = std::fs::read_to_string(path)?;
+        Ok(ScriptSource { name, source })
+    }
+
+    pub fn read_all(dir: &Path) -> Vec<Result<Self>> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return vec![];
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+            .collect();
+        paths.sort();
+        paths.iter().map(|path| Self::read(path)).collect()
+    }
+}
+
+pub struct ScriptLayout {
+    pub name: String,
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptLayout {
+    pub fn compile(name: &str, source: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_array_size(MAX_ARRAY_SIZE)
+            .set_max_string_size(MAX_STRING_SIZE)
+            .set_max_map_size(MAX_MAP_SIZE)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_FUNCTION_EXPR_DEPTH);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| format!("Could not compile layout script '{name}': {e}"))?;
+        Ok(ScriptLayout {
+            name: name.to_owned(),
+            engine,
+            ast,
+        })
+    }
+
+    pub fn try_arrange(&self, bounds: Rect, n: usize) -> std::result::Result<Vec<Rect>, String> {
+        // Empty monitors are arranged too, scripts need not handle it
+        if n == 0 {
+            return Ok(vec![]);
+        }
+        let result: Array = self
+            .engine
+            .call_fn(
+                &mut Scope::new(),
+                &self.ast,
+                "layout",
+                (to_map(bounds), n as i64),
+            )
+            .map_err(|e| e.to_string())?;
+        if result.len() != n {
+            return Err(format!("returned {} rects for {} windows", result.len(), n));
+        }
+        result
+            .into_iter()
+            .enumerate()
+            .map(|(idx, value)| {
+                let rect = to_rect(value).ok_or(format!(
+                    "rect {idx} is not a map of left, top, right and bottom"
+                ))?;
+                let is_inside = rect.left >= bounds.left
+                    && rect.top >= bounds.top
+                    && rect.right <= bounds.right
+                    && rect.bottom <= bounds.bottom;
+                let is_empty = rect.right <= rect.left || rect.bottom <= rect.top;
+                if !is_inside || is_empty {
+                    return Err(format!(
+                        "rect {idx} {rect:?} is not a valid rect in {bounds:?}"
+                    ));
+                }
+                Ok(rect)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for ScriptLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptLayout")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl LayoutEngine for ScriptLayout {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn arrange(&self, bounds: Rect, n: usize, params: &LayoutParams) -> Vec<Rect> {
+        self.try_arrange(bounds, n).unwrap_or_else(|e| {
+            error!("Layout script '{}' failed: {e}", self.name);
+            dwindle(bounds, n, params)
+        })
+    }
+}
+
+fn to_map(rect: Rect) -> Map {
+    let mut map = Map::new();
+    map.insert("left".into(), Dynamic::from_int(rect.left as i64));
+    map.insert("top".into(), Dynamic::from_int(rect.top as i64));
+    map.insert("right".into(), Dynamic::from_int(rect.right as i64));
+    map.insert("bottom".into(), Dynamic::from_int(rect.bottom as i64));
+    map
+}
+
+fn to_rect(value: Dynamic) -> Option<Rect> {
+    let map = value.try_cast::<Map>()?;
+    let field = |key: &str| -> Option<i32> {
+        map.get(key)?
+            .as_int()
+            .ok()
+            .and_then(|v| i32::try_from(v).ok())
+    };
+    Some(Rect {
+        left: field("left")?,
+        top: field("top")?,
+        right: field("right")?,
+        bottom: field("bottom")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLUMNS: &str = r#"
+        fn layout(bounds, n) {
+            let width = (bounds.right - bounds.left) / n;
+            let rects = [];
+            for i in 0..n {
+                let left = bounds.left + i * width;
+                rects.push(#{ left: left, top: bounds.top, right: left + width, bottom: bounds.bottom });
+            }
+            rects
+        }
+    "#;
+
+    fn bounds() -> Rect {
+        Rect {
+            left: 100,
+            top: 0,
+            right: 1100,
+            bottom: 500,
+        }
+    }
+
+    #[test]
+    fn script_returns_rects() {
+        let script = ScriptLayout::compile("columns", COLUMNS).unwrap();
+        let rs = script.try_arrange(bounds(), 2).unwrap();
+        assert_eq!(
+            rs,
+            vec![
+                Rect {
+                    left: 100,
+                    top: 0,
+                    right: 600,
+                    bottom: 500
+                },
+                Rect {
+                    left: 600,
+                    top: 0,
+                    right: 1100,
+                    bottom: 500
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn script_is_not_run_without_windows() {
+        let script = ScriptLayout::compile("columns", COLUMNS).unwrap();
+        assert_eq!(script.try_arrange(bounds(), 0), Ok(vec![]));
+    }
+
+    #[test]
+    fn script_wrong_count_is_an_error() {
+        let script = ScriptLayout::compile("one", "fn layout(bounds, n) { [bounds] }").unwrap();
+        assert_eq!(
+            script.try_arrange(bounds(), 2).unwrap_err(),
+            "returned 1 rects for 2 windows"
+        );
+    }
+
+    #[test]
+    fn script_out_of_bounds_is_an_error() {
+        let script = ScriptLayout::compile(
+            "wide",
+            "fn layout(bounds, n) { bounds.right += 1; [bounds] }",
+        )
+        .unwrap();
+        assert!(script.try_arrange(bounds(), 1).is_err());
+    }
+
+    #[test]
+    fn script_runaway_is_an_error() {
+        let script = ScriptLayout::compile("loop", "fn layout(bounds, n) { loop {} }").unwrap();
+        assert!(script.try_arrange(bounds(), 1).is_err());
+    }
+
+    #[test]
+    fn runaway_recursion_and_allocation_fall_back_to_dwindle() {
+        let params = LayoutParams::default();
+        for source in [
+            "fn deeper(depth) { deeper(depth + 1) } fn layout(bounds, n) { deeper(0) }",
+            "fn layout(bounds, n) { let a = [bounds]; loop { a += a; } }",
+            "fn layout(bounds, n) { let s = \"x\"; loop { s += s; } }",
+        ] {
+            let script = ScriptLayout::compile("runaway", source).unwrap();
+            assert!(script.try_arrange(bounds(), 3).is_err(), "{source}");
+            assert_eq!(
+                script.arrange(bounds(), 3, &params),
+                dwindle(bounds(), 3, &params)
+            );
+        }
+    }
+
+    #[test]
+    fn script_syntax_error_is_reported() {
+        assert!(ScriptLayout::compile("broken", "fn layout(bounds, n) {").is_err());
+    }
+
+    #[test]
+    fn failing_script_falls_back_to_dwindle() {
+        let script = ScriptLayout::compile("broken", "fn layout(bounds, n) { [] }").unwrap();
+        let params = LayoutParams::default();
+        assert_eq!(
+            script.arrange(bounds(), 3, &params),
+            dwindle(bounds(), 3, &params)
+        );
+    }
+}
@@ -0,0 +1,538 @@
+use std::{
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use log::error;
+use serde::{Deserialize, Serialize};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, WPARAM},
+    UI::WindowsAndMessaging::{
+        HSHELL_WINDOWACTIVATED, HSHELL_WINDOWCREATED, HSHELL_WINDOWDESTROYED, WM_COMMAND,
+        WM_DISPLAYCHANGE,
+    },
+};
+
+use crate::{
+    backend::{
+        fake::{FakeBackend, FakeWindow},
+        Backend,
+    },
+    script::ScriptSource,
+    windowmanager::{
+        WindowManager, MSG_CLOAKED, MSG_MINIMIZEEND, MSG_MINIMIZESTART, MSG_MOVESIZEEND,
+        MSG_UNCLOAKED, SHELL_HOOK_ID,
+    },
+};
+use grout_core::{
+    config::Config, desktops::DesktopId, monitors::Monitor, window::WindowInfo, Point, Rect,
+};
+use grout_wm::{Result, LOWORD};
+
+// Registered window messages live in 0xC000..=0xFFFF, any of them will do
+const REPLAY_SHELL_HOOK: u32 = 0xC000;
+
+/// An event reaching `WindowManager::message_loop`, independent of the
+/// message ids of the session it was recorded in.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    /// The window manager picking up the windows already open.
+    Start,
+    /// A new config and layout scripts were swapped in.
+    ConfigReloaded,
+    DisplayChange,
+    Command(u32),
+    /// Switching layouts, by name as layout ids differ between sessions.
+    SetLayout(String),
+    Cloaked,
+    Uncloaked,
+    MinimizeStart,
+    MinimizeEnd,
+    MoveSizeEnd,
+    WindowCreated,
+    WindowDestroyed,
+    WindowActivated,
+}
+
+impl Event {
+    pub fn from_message(msg: u32, wparam: WPARAM, shell_hook_id: u32) -> Option<Self> {
+        let event = match msg {
+            WM_DISPLAYCHANGE => Event::DisplayChange,
+            WM_COMMAND => Event::Command(LOWORD!(wparam.0) as u32),
+            MSG_CLOAKED => Event::Cloaked,
+            MSG_UNCLOAKED => Event::Uncloaked,
+            MSG_MINIMIZESTART => Event::MinimizeStart,
+            MSG_MINIMIZEEND => Event::MinimizeEnd,
+            MSG_MOVESIZEEND => Event::MoveSizeEnd,
+            id if id == shell_hook_id => match LOWORD!(wparam.0) as u32 {
+                HSHELL_WINDOWCREATED => Event::WindowCreated,
+                HSHELL_WINDOWDESTROYED => Event::WindowDestroyed,
+                HSHELL_WINDOWACTIVATED => Event::WindowActivated,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(event)
+    }
+
+    /// The message to replay the event with, none for events the window
+    /// manager has no message for.
+    pub fn to_message(&self, hwnd: HWND, shell_hook_id: u32) -> Option<(u32, WPARAM, LPARAM)> {
+        let lparam = LPARAM(hwnd.0);
+        let message = match *self {
+            Event::Start | Event::ConfigReloaded | Event::SetLayout(_) => return None,
+            Event::DisplayChange => (WM_DISPLAYCHANGE, WPARAM(0), LPARAM(0)),
+            Event::Command(id) => (WM_COMMAND, WPARAM(id as usize), LPARAM(0)),
+            Event::Cloaked => (MSG_CLOAKED, WPARAM(0), lparam),
+            Event::Uncloaked => (MSG_UNCLOAKED, WPARAM(0), lparam),
+            Event::MinimizeStart => (MSG_MINIMIZESTART, WPARAM(0), lparam),
+            Event::MinimizeEnd => (MSG_MINIMIZEEND, WPARAM(0), lparam),
+            Event::MoveSizeEnd => (MSG_MOVESIZEEND, WPARAM(0), lparam),
+            Event::WindowCreated => (shell_hook_id, WPARAM(HSHELL_WINDOWCREATED as usize), lparam),
+            Event::WindowDestroyed => (
+                shell_hook_id,
+                WPARAM(HSHELL_WINDOWDESTROYED as usize),
+                lparam,
+            ),
+            Event::WindowActivated => (
+                shell_hook_id,
+                WPARAM(HSHELL_WINDOWACTIVATED as usize),
+                lparam,
+            ),
+        };
+        Some(message)
+    }
+
+    /// Whether the message carries the window it is about.
+    pub fn has_window(&self) -> bool {
+        !matches!(
+            self,
+            Event::Start
+                | Event::ConfigReloaded
+                | Event::DisplayChange
+                | Event::Command(_)
+                | Event::SetLayout(_)
+        )
+    }
+}
+
+/// A window as the backend reported it when an event came in.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WindowSnapshot {
+    pub hwnd: isize,
+    pub info: WindowInfo,
+    pub rect: Rect,
+    pub is_iconic: bool,
+    pub desktop: Option<DesktopId>,
+    pub on_current_desktop: bool,
+}
+
+impl WindowSnapshot {
+    pub fn capture(backend: &dyn Backend, hwnd: HWND) -> Self {
+        WindowSnapshot {
+            hwnd: hwnd.0,
+            info: backend.window_info(hwnd),
+            rect: backend.window_rect(hwnd),
+            is_iconic: backend.is_iconic(hwnd),
+            desktop: backend.desktop_of(hwnd),
+            on_current_desktop: backend.is_on_current_desktop(hwnd),
+        }
+    }
+}
+
+/// One line of a trace: an event and the state the window manager sees
+/// when handling it.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Record {
+    pub event: Event,
+    pub hwnd: isize,
+    pub cursor: Point,
+    pub foreground: isize,
+    /// Desktop the user was looking at, missing when the platform can't tell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desktop: Option<DesktopId>,
+    /// Only recorded on start and when the config is reloaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Config>,
+    /// Layout scripts, recorded along with the config.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scripts: Vec<ScriptSource>,
+    /// Only recorded on start and when the displays change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitors: Option<Vec<Monitor>>,
+    pub windows: Vec<WindowSnapshot>,
+}
+
+impl Record {
+    pub fn capture(
+        backend: &dyn Backend,
+        event: Event,
+        hwnd: HWND,
+        windows: impl IntoIterator<Item = HWND>,
+    ) -> Self {
+        let monitors = match event {
+            Event::Start | Event::DisplayChange => backend
+                .monitors()
+                .inspect_err(|e| error!("Can not get monitors: {e}"))
+                .ok(),
+            _ => None,
+        };
+        Record {
+            event,
+            hwnd: hwnd.0,
+            cursor: backend.cursor_pos(),
+            foreground: backend.foreground_window().0,
+            desktop: backend.current_desktop(),
+            config: None,
+            scripts: vec![],
+            monitors,
+            windows: windows
+                .into_iter()
+                .map(|hwnd| WindowSnapshot::capture(backend, hwnd))
+                .collect(),
+        }
+    }
+}
+
+/// Writes records as JSON lines, flushing each so a trace survives a crash.
+pub struct Recorder(Box<dyn Write>);
+
+impl Recorder {
+    #[cfg(any(windows, test))]
+    pub fn new(out: impl Write + 'static) -> Self {
+        Recorder(Box::new(out))
+    }
+
+    pub fn write(&mut self, record: &Record) {
+        let res = serde_json::to_writer(&mut self.0, record)
+            .map_err(io::Error::from)
+            .and_then(|()| writeln!(self.0))
+            .and_then(|()| self.0.flush());
+        if let Err(e) = res {
+            error!("Can not write trace: {e}");
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlacedWindow {
+    pub hwnd: isize,
+    pub title: String,
+    pub rect: Rect,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorLayout {
+    pub layout: String,
+    pub windows: Vec<PlacedWindow>,
+}
+
+/// Layouts of all monitors after an event was handled.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub event: Event,
+    pub monitors: Vec<MonitorLayout>,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:?}", self.event)?;
+        for (idx, monitor) in self.monitors.iter().enumerate() {
+            writeln!(f, "  monitor {idx}: {}", monitor.layout)?;
+            for PlacedWindow { hwnd, title, rect } in &monitor.windows {
+                let Rect {
+                    left,
+                    top,
+                    right,
+                    bottom,
+                } = rect;
+                writeln!(
+                    f,
+                    "    {hwnd:#x} {title:?} ({left}, {top}, {right}, {bottom})"
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Windows missing a desktop id are put on one no other window is on
+fn fake_window(snapshot: &WindowSnapshot, current: Option<DesktopId>) -> FakeWindow {
+    let desktop = match (snapshot.on_current_desktop, current) {
+        (true, Some(current)) => current,
+        _ => snapshot.desktop.unwrap_or(DesktopId::MAX),
+    };
+    FakeWindow {
+        hwnd: HWND(snapshot.hwnd),
+        info: snapshot.info.clone(),
+        rect: snapshot.rect,
+        min_size: Default::default(),
+        is_iconic: snapshot.is_iconic,
+        desktop,
+    }
+}
+
+fn apply(backend: &FakeBackend, record: &Record) {
+    if let Some(monitors) = &record.monitors {
+        backend.set_monitors(monitors.clone());
+    }
+    backend.set_cursor_pos(record.cursor);
+    let current = record.desktop.or_else(|| {
+        record
+            .windows
+            .iter()
+            .find(|w| w.on_current_desktop)
+            .and_then(|w| w.desktop)
+    });
+    if let Some(current) = current {
+        backend.switch_desktop(current);
+    }
+    for snapshot in &record.windows {
+        backend.put(fake_window(snapshot, current));
+    }
+    backend.set_foreground_window(HWND(record.foreground));
+    if record.event == Event::WindowDestroyed {
+        backend.close(HWND(record.hwnd));
+    }
+}
+
+fn step(wm: &WindowManager, backend: &FakeBackend, event: Event) -> Step {
+    let monitors = wm
+        .monitor_layouts()
+        .into_iter()
+        .map(|(layout, windows)| MonitorLayout {
+            layout,
+            windows: windows
+                .into_iter()
+                .map(|hwnd| PlacedWindow {
+                    hwnd: hwnd.0,
+                    title: backend.window_info(hwnd).title,
+                    rect: backend.rect(hwnd),
+                })
+                .collect(),
+        })
+        .collect();
+    Step { event, monitors }
+}
+
+/// Feeds a recorded trace to a window manager running on the fake backend
+/// and returns the layouts after every event. Windows refusing to shrink
+/// are not reproduced, the fake backend places them where asked.
+pub fn replay(trace: impl BufRead) -> Result<Vec<Step>> {
+    let mut records = trace.lines().filter(|line| {
+        line.as_ref()
+            .map(|line| !line.trim().is_empty())
+            .unwrap_or(true)
+    });
+    let start: Record = match records.next() {
+        Some(line) => serde_json::from_str(&line?).map_err(|e| format!("Invalid trace: {e}"))?,
+        None => return Err("Trace is empty".into()),
+    };
+    let config = match (&start.event, start.config.clone()) {
+        (Event::Start, Some(config)) => config,
+        _ => return Err("Trace does not start with the config".into()),
+    };
+    let shell_hook_id = *SHELL_HOOK_ID.get_or_init(|| REPLAY_SHELL_HOOK);
+    let backend = FakeBackend::default();
+    apply(&backend, &start);
+    // Only the recorded scripts, not the ones on this machine
+    let mut wm = WindowManager::new(config, start.scripts, Box::new(backend.clone()))?;
+    wm.enum_windows();
+    let mut steps = vec![step(&wm, &backend, Event::Start)];
+    for (idx, line) in records.enumerate() {
+        let line_number = idx + 2;
+        let record: Record = serde_json::from_str(&line?)
+            .map_err(|e| format!("Invalid trace line {line_number}: {e}"))?;
+        apply(&backend, &record);
+        let message = match &record.event {
+            Event::ConfigReloaded => {
+                let config = record.config.clone().ok_or(format!(
+                    "Trace line {line_number} reloads the config without one"
+                ))?;
+                wm.reload_config(config, record.scripts.clone())?;
+                None
+            }
+            Event::SetLayout(name) => match wm.layout_command(name) {
+                Some(command) => Some((WM_COMMAND, WPARAM(command as usize), LPARAM(0))),
+                None => {
+                    return Err(
+                        format!("Trace line {line_number} sets unknown layout {name}").into(),
+                    )
+                }
+            },
+            event => event.to_message(HWND(record.hwnd), shell_hook_id),
+        };
+        if let Some((msg, wparam, lparam)) = message {
+            wm.message_loop(msg, wparam, lparam);
+        }
+        steps.push(step(&wm, &backend, record.event));
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::windowmanager::CMD_NEXT_LAYOUT;
+    use grout_core::ipc::{Request, Response};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    #[test]
+    fn events_survive_the_round_trip_through_messages() {
+        let shell_hook_id = 0xC123;
+        for event in [
+            Event::DisplayChange,
+            Event::Command(CMD_NEXT_LAYOUT),
+            Event::Cloaked,
+            Event::Uncloaked,
+            Event::MinimizeStart,
+            Event::MinimizeEnd,
+            Event::MoveSizeEnd,
+            Event::WindowCreated,
+            Event::WindowDestroyed,
+            Event::WindowActivated,
+        ] {
+            let (msg, wparam, _) = event.to_message(HWND(7), shell_hook_id).unwrap();
+            assert_eq!(Event::from_message(msg, wparam, shell_hook_id), Some(event));
+        }
+        for event in [
+            Event::Start,
+            Event::ConfigReloaded,
+            Event::SetLayout("Dwindle".to_owned()),
+        ] {
+            assert_eq!(event.to_message(HWND(7), shell_hook_id), None);
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_layouts() {
+        let area = rect(0, 0, 1000, 600);
+        let backend = FakeBackend::new(vec![Monitor {
+            area,
+            work_area: area,
+        }]);
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        backend.open(2, "b", rect(10, 10, 200, 200));
+        let shell_hook_id = *SHELL_HOOK_ID.get_or_init(|| REPLAY_SHELL_HOOK);
+        let config = serde_yaml::from_str(include_str!("../default.yaml")).unwrap();
+        let mut wm = WindowManager::new(config, vec![], Box::new(backend.clone())).unwrap();
+        let trace = SharedBuffer::default();
+        wm.record_to(Recorder::new(trace.clone()));
+        wm.enum_windows();
+        let c = backend.open(3, "c", rect(10, 10, 200, 200));
+        let events = [
+            (Event::WindowCreated, c),
+            (Event::Command(CMD_NEXT_LAYOUT), HWND(0)),
+            (Event::WindowDestroyed, a),
+        ];
+        for (event, hwnd) in events {
+            if event == Event::WindowDestroyed {
+                backend.close(hwnd);
+            }
+            let (msg, wparam, lparam) = event.to_message(hwnd, shell_hook_id).unwrap();
+            assert!(wm.message_loop(msg, wparam, lparam));
+        }
+
+        let steps = replay(&trace.0.borrow()[..]).unwrap();
+        let replayed: Vec<Event> = steps.iter().map(|step| step.event.clone()).collect();
+        assert_eq!(
+            replayed,
+            [
+                Event::Start,
+                Event::WindowCreated,
+                Event::Command(CMD_NEXT_LAYOUT),
+                Event::WindowDestroyed
+            ]
+        );
+        let last = steps.last().unwrap();
+        assert_eq!(last, &step(&wm, &backend, Event::WindowDestroyed));
+        assert_eq!(last.monitors[0].windows.len(), 2);
+        assert_ne!(steps[1].monitors[0].layout, last.monitors[0].layout);
+    }
+
+    fn script(name: &str, split: &str) -> ScriptSource {
+        let source = format!(
+            r#"
+            fn layout(bounds, n) {{
+                let rects = [];
+                for i in 0..n {{
+                    let r = bounds;
+                    {split}
+                    rects.push(r);
+                }}
+                rects
+            }}
+            "#
+        );
+        ScriptSource {
+            name: name.to_owned(),
+            source,
+        }
+    }
+
+    #[test]
+    fn replay_uses_the_recorded_scripts_and_reloads() {
+        let area = rect(0, 0, 1000, 600);
+        let backend = FakeBackend::new(vec![Monitor {
+            area,
+            work_area: area,
+        }]);
+        backend.open(1, "a", rect(10, 10, 200, 200));
+        backend.open(2, "b", rect(10, 10, 200, 200));
+        let columns = "let w = (bounds.right - bounds.left) / n; \
+            r.left = bounds.left + i * w; r.right = r.left + w;";
+        let rows = "let h = (bounds.bottom - bounds.top) / n; \
+            r.top = bounds.top + i * h; r.bottom = r.top + h;";
+        let config = || serde_yaml::from_str(include_str!("../default.yaml")).unwrap();
+        let scripts = vec![script("split", columns)];
+        let mut wm = WindowManager::new(config(), scripts, Box::new(backend.clone())).unwrap();
+        let trace = SharedBuffer::default();
+        wm.record_to(Recorder::new(trace.clone()));
+        wm.enum_windows();
+        let request = Request::SetLayout {
+            layout: "split".to_owned(),
+        };
+        assert!(matches!(wm.handle_request(request), Response::Ok));
+        let as_columns = step(&wm, &backend, Event::SetLayout("split".to_owned()));
+        wm.reload_config(config(), vec![script("split", rows)])
+            .unwrap();
+
+        let steps = replay(&trace.0.borrow()[..]).unwrap();
+        let replayed: Vec<Event> = steps.iter().map(|step| step.event.clone()).collect();
+        assert_eq!(
+            replayed,
+            [
+                Event::Start,
+                Event::SetLayout("split".to_owned()),
+                Event::ConfigReloaded
+            ]
+        );
+        assert_eq!(steps[1], as_columns);
+        assert_eq!(steps[2], step(&wm, &backend, Event::ConfigReloaded));
+        assert_ne!(steps[1].monitors[0].windows, steps[2].monitors[0].windows);
+    }
+}
@@ -0,0 +1,125 @@
+use log::error;
+use windows::Win32::{
+    Foundation::{BOOL, HWND, LPARAM, POINT, TRUE},
+    UI::WindowsAndMessaging::GW_OWNER,
+};
+
+use crate::{
+    backend::Backend,
+    win32::{
+        self,
+        virtualdesktop::{self, VirtualDesktopManager},
+    },
+};
+use grout_core::{
+    desktops::{DesktopId, DesktopProvider},
+    monitors::Monitor,
+    window::WindowInfo,
+    Point, Rect,
+};
+use grout_wm::Result;
+
+pub struct Win32Backend {
+    virtual_desktop: VirtualDesktopManager,
+}
+
+impl Win32Backend {
+    pub fn new() -> Result<Self> {
+        Ok(Win32Backend {
+            virtual_desktop: VirtualDesktopManager::new()?,
+        })
+    }
+}
+
+extern "system" fn collect_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let windows: &mut Vec<HWND> = unsafe { &mut *(lparam.0 as *mut Vec<HWND>) };
+    windows.push(hwnd);
+    TRUE
+}
+
+impl DesktopProvider<HWND> for Win32Backend {
+    fn desktop_of(&self, hwnd: HWND) -> Option<DesktopId> {
+        self.virtual_desktop
+            .get_window_desktop_id(hwnd)
+            .ok()
+            .map(|guid| guid.to_u128())
+    }
+
+    fn is_on_current_desktop(&self, hwnd: HWND) -> bool {
+        self.virtual_desktop
+            .is_window_on_current_desktop(hwnd)
+            .unwrap_or(false)
+    }
+
+    fn current_desktop(&self) -> Option<DesktopId> {
+        virtualdesktop::current_desktop_id().map(|guid| guid.to_u128())
+    }
+}
+
+impl Backend for Win32Backend {
+    fn windows(&self) -> Vec<HWND> {
+        let mut windows: Vec<HWND> = vec![];
+        let lparam = LPARAM(&mut windows as *mut Vec<HWND> as isize);
+        if !win32::enum_windows(Some(collect_window), lparam) {
+            error!("Can not enum windows");
+        }
+        windows
+    }
+
+    fn window_info(&self, hwnd: HWND) -> WindowInfo {
+        WindowInfo {
+            title: win32::get_window_text(hwnd),
+            class_name: win32::get_window_classname(hwnd),
+            process_name: win32::get_exe_filename(hwnd),
+            style: win32::get_window_style(hwnd),
+            exstyle: win32::get_window_exstyle(hwnd),
+            is_visible: win32::is_window_visible(hwnd),
+            is_cloaked: win32::dwm::is_cloaked(hwnd),
+            has_owner: win32::get_window(hwnd, GW_OWNER).0 != 0,
+        }
+    }
+
+    fn is_iconic(&self, hwnd: HWND) -> bool {
+        win32::is_iconic(hwnd)
+    }
+
+    fn window_rect(&self, hwnd: HWND) -> Rect {
+        win32::from_rect(win32::get_window_rect(hwnd))
+    }
+
+    fn visible_frame(&self, hwnd: HWND) -> Rect {
+        win32::from_rect(win32::dwm::get_visible_frame(hwnd))
+    }
+
+    fn set_window_positions(&self, positions: &[(HWND, Rect)]) {
+        if let Ok(mut hdwp) = win32::begin_defer_window_pos(positions.len()) {
+            for &(hwnd, rect) in positions {
+                if let Ok(res) = win32::defer_window_pos(hdwp, hwnd, win32::to_rect(rect)) {
+                    hdwp = res;
+                }
+            }
+            win32::end_defer_window_pos(hdwp);
+        }
+    }
+
+    fn foreground_window(&self) -> HWND {
+        win32::get_foreground_window()
+    }
+
+    fn set_foreground_window(&self, hwnd: HWND) -> bool {
+        win32::set_foreground_window(hwnd)
+    }
+
+    fn cursor_pos(&self) -> Point {
+        let POINT { x, y } = win32::get_cursor_pos();
+        Point { x, y }
+    }
+
+    fn monitors(&self) -> Result<Vec<Monitor>> {
+        win32::get_monitors()
+    }
+
+    fn invalidate_thumbnail(&self, hwnd: HWND) {
+        let _ = win32::dwm::invalidate_iconic_bitmaps(hwnd);
+    }
+}
@@ -30,6 +30,19 @@ pub fn get_window_extended_frame_bounds(hwnd: HWND) -> RECT {
     }
 }
 
+pub fn get_visible_frame(hwnd: HWND) -> RECT {
+    let mut frame: RECT = unsafe { zeroed() };
+    unsafe {
+        let _ = DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            &mut frame as *mut RECT as *mut c_void,
+            size_of::<RECT>().try_into().unwrap(),
+        );
+    }
+    frame
+}
+
 pub fn is_cloaked(hwnd: HWND) -> bool {
     let mut cloaked: u32 = 0;
     let res = unsafe {
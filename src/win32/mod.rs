@@ -9,10 +9,10 @@ use windows::{
     core::{w, PCWSTR},
     Win32::{
         Foundation::{
-            CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, FALSE, HANDLE, HMODULE, HWND, LPARAM,
-            LRESULT, MAX_PATH, POINT, RECT, TRUE, WPARAM,
+            CloseHandle, GetLastError, BOOL, ERROR_ALREADY_EXISTS, FALSE, HANDLE, HMODULE, HWND,
+            LPARAM, LRESULT, MAX_PATH, POINT, RECT, TRUE, WPARAM,
         },
-        Graphics::Gdi::PtInRect,
+        Graphics::Gdi::{EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO},
         System::{
             LibraryLoader::GetModuleHandleA,
             ProcessStatus::{
@@ -27,24 +27,27 @@ use windows::{
             Shell::{FOLDERID_LocalAppData, SHGetKnownFolderPath, KF_FLAG_DEFAULT},
             WindowsAndMessaging::{
                 BeginDeferWindowPos, DefWindowProcW, DeferWindowPos, EndDeferWindowPos,
-                EnumWindows, FindWindowW, GetClassNameW, GetCursorPos, GetSystemMetrics, GetWindow,
-                GetWindowLongPtrW, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId,
-                IsIconic, IsWindowVisible, LoadIconW, PostMessageW, PostQuitMessage,
-                RegisterClassW, RegisterShellHookWindow, RegisterWindowMessageW, SetWindowLongPtrW,
-                ShowWindow, SystemParametersInfoW, GET_WINDOW_CMD, GWL_EXSTYLE, GWL_STYLE, HDWP,
-                HICON, HWND_TOP, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN,
-                SM_YVIRTUALSCREEN, SPI_GETWORKAREA, SWP_NOACTIVATE, SW_SHOWMINNOACTIVE,
-                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, WINDOW_LONG_PTR_INDEX, WINEVENT_OUTOFCONTEXT,
-                WNDCLASSW, WNDENUMPROC,
+                EnumWindows, FindWindowW, GetClassNameW, GetCursorPos, GetForegroundWindow,
+                GetSystemMetrics, GetWindow, GetWindowLongPtrW, GetWindowRect, GetWindowTextW,
+                GetWindowThreadProcessId, IsIconic, IsWindowVisible, LoadIconW, PostMessageW,
+                PostQuitMessage, RegisterClassW, RegisterShellHookWindow, RegisterWindowMessageW,
+                SetForegroundWindow, SetWindowLongPtrW, ShowWindow, SystemParametersInfoW,
+                GET_WINDOW_CMD, GWL_EXSTYLE, GWL_STYLE, HDWP, HICON, HWND_TOP, SM_CXVIRTUALSCREEN,
+                SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SPI_GETWORKAREA,
+                SWP_NOACTIVATE, SW_SHOWMINNOACTIVE, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+                WINDOW_LONG_PTR_INDEX, WINEVENT_OUTOFCONTEXT, WNDCLASSW, WNDENUMPROC,
             },
         },
     },
 };
 
+use grout_core::{monitors::Monitor, Rect};
 use grout_wm::Result;
 
+pub(crate) mod backend;
 pub(crate) mod com;
 pub(crate) mod dwm;
+pub(crate) mod pipe;
 pub(crate) mod taskbar;
 pub(crate) mod theme;
 pub(crate) mod virtualdesktop;
@@ -97,8 +100,43 @@ pub fn is_window_visible(hwnd: HWND) -> bool {
     unsafe { IsWindowVisible(hwnd).into() }
 }
 
-pub fn get_window_text_length(hwnd: HWND) -> i32 {
-    unsafe { GetWindowTextLengthW(hwnd) }
+pub fn from_rect(rect: RECT) -> Rect {
+    let RECT {
+        left,
+        top,
+        right,
+        bottom,
+    } = rect;
+    Rect {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+pub fn to_rect(rect: Rect) -> RECT {
+    let Rect {
+        left,
+        top,
+        right,
+        bottom,
+    } = rect;
+    RECT {
+        left,
+        top,
+        right,
+        bottom,
+    }
+}
+
+pub fn get_window_rect(hwnd: HWND) -> RECT {
+    let mut rect: RECT = unsafe { zeroed() };
+    let res = unsafe { GetWindowRect(hwnd, &mut rect) };
+    if res.is_err() {
+        error!("GetWindowRect failed: {:?}", res);
+    }
+    rect
 }
 
 pub fn get_working_area() -> Result<RECT> {
@@ -128,6 +166,42 @@ pub fn get_working_area() -> Result<RECT> {
     }
 }
 
+extern "system" fn collect_monitor(
+    hmonitor: HMONITOR,
+    _hdc: HDC,
+    _rect: *mut RECT,
+    lparam: LPARAM,
+) -> BOOL {
+    let monitors: &mut Vec<Monitor> = unsafe { &mut *(lparam.0 as *mut Vec<Monitor>) };
+    let mut mi = MONITORINFO {
+        cbSize: size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if unsafe { GetMonitorInfoW(hmonitor, &mut mi) }.as_bool() {
+        monitors.push(Monitor {
+            area: from_rect(mi.rcMonitor),
+            work_area: from_rect(mi.rcWork),
+        });
+    } else {
+        error!("GetMonitorInfoW failed for {hmonitor:?}");
+    }
+    TRUE
+}
+
+pub fn get_monitors() -> Result<Vec<Monitor>> {
+    let mut monitors: Vec<Monitor> = vec![];
+    let lparam = LPARAM(&mut monitors as *mut Vec<Monitor> as isize);
+    let res = unsafe { EnumDisplayMonitors(HDC::default(), None, Some(collect_monitor), lparam) };
+    if !res.as_bool() || monitors.is_empty() {
+        let area = from_rect(get_working_area()?);
+        return Ok(vec![Monitor {
+            area,
+            work_area: area,
+        }]);
+    }
+    Ok(monitors)
+}
+
 pub fn get_module_handle() -> windows::core::Result<HMODULE> {
     unsafe { GetModuleHandleA(None) }
 }
@@ -291,8 +365,12 @@ pub fn get_cursor_pos() -> POINT {
     p
 }
 
-pub fn point_in_rect(lprc: RECT, pt: POINT) -> bool {
-    unsafe { PtInRect(&lprc, pt).into() }
+pub fn get_foreground_window() -> HWND {
+    unsafe { GetForegroundWindow() }
+}
+
+pub fn set_foreground_window(hwnd: HWND) -> bool {
+    unsafe { SetForegroundWindow(hwnd) }.as_bool()
 }
 
 pub fn load_icon(hinstance: HMODULE, lpiconname: PCWSTR) -> windows::core::Result<HICON> {
@@ -0,0 +1,80 @@
+use std::io::{self, Read, Write};
+
+use windows::{
+    core::HSTRING,
+    Win32::{
+        Foundation::{CloseHandle, ERROR_BROKEN_PIPE, ERROR_PIPE_CONNECTED, HANDLE},
+        Storage::FileSystem::{FlushFileBuffers, ReadFile, WriteFile, PIPE_ACCESS_DUPLEX},
+        System::Pipes::{
+            ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_READMODE_BYTE,
+            PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+        },
+    },
+};
+
+use grout_wm::Result;
+
+const BUFFER_SIZE: u32 = 4096;
+
+/// Server end of a named pipe with a client connected to it.
+pub struct PipeConnection(HANDLE);
+
+impl PipeConnection {
+    /// Creates an instance of the pipe and blocks until a client connects.
+    pub fn accept(name: &str) -> Result<Self> {
+        let handle = unsafe {
+            CreateNamedPipeW(
+                &HSTRING::from(name),
+                PIPE_ACCESS_DUPLEX,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                None,
+            )
+        };
+        if handle.is_invalid() {
+            return Err(windows::core::Error::from_win32().into());
+        }
+        let connection = PipeConnection(handle);
+        match unsafe { ConnectNamedPipe(handle, None) } {
+            // The client got in between creating the pipe and waiting for it
+            Err(e) if e.code() != ERROR_PIPE_CONNECTED.to_hresult() => Err(e.into()),
+            _ => Ok(connection),
+        }
+    }
+}
+
+impl Read for PipeConnection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+        match unsafe { ReadFile(self.0, Some(buf), Some(&mut read), None) } {
+            Err(e) if e.code() == ERROR_BROKEN_PIPE.to_hresult() => Ok(0),
+            res => res.map(|()| read as usize).map_err(io::Error::other),
+        }
+    }
+}
+
+impl Write for PipeConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+        unsafe { WriteFile(self.0, Some(buf), Some(&mut written), None) }
+            .map_err(io::Error::other)?;
+        Ok(written as usize)
+    }
+
+    // Waits for the client to read everything written so far
+    fn flush(&mut self) -> io::Result<()> {
+        unsafe { FlushFileBuffers(self.0) }.map_err(io::Error::other)
+    }
+}
+
+impl Drop for PipeConnection {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DisconnectNamedPipe(self.0);
+            let _ = CloseHandle(self.0);
+        }
+    }
+}
@@ -1,7 +1,15 @@
 use log::info;
-use windows::Win32::{
-    Foundation::HWND,
-    UI::Shell::{IVirtualDesktopManager, VirtualDesktopManager as VirtualDesktopManager_ID},
+use windows::{
+    core::{GUID, HSTRING},
+    Win32::{
+        Foundation::{ERROR_SUCCESS, HWND},
+        System::{
+            Registry::{RegGetValueW, HKEY_CURRENT_USER, RRF_RT_REG_BINARY},
+            RemoteDesktop::ProcessIdToSessionId,
+            Threading::GetCurrentProcessId,
+        },
+        UI::Shell::{IVirtualDesktopManager, VirtualDesktopManager as VirtualDesktopManager_ID},
+    },
 };
 
 use grout_wm::Result;
@@ -21,4 +29,49 @@ impl VirtualDesktopManager {
         let is_on_desktop = unsafe { self.0.IsWindowOnCurrentVirtualDesktop(hwnd)? };
         Ok(is_on_desktop.as_bool())
     }
+
+    pub fn get_window_desktop_id(&self, hwnd: HWND) -> windows::core::Result<GUID> {
+        unsafe { self.0.GetWindowDesktopId(hwnd) }
+    }
+}
+
+const EXPLORER_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer";
+
+fn read_desktop_id(key: &str) -> Option<GUID> {
+    let mut buffer = [0u8; 16];
+    let mut size = buffer.len() as u32;
+    let res = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &HSTRING::from(key),
+            &HSTRING::from("CurrentVirtualDesktop"),
+            RRF_RT_REG_BINARY,
+            None,
+            Some(buffer.as_mut_ptr() as *mut _),
+            Some(&mut size),
+        )
+    };
+    if res != ERROR_SUCCESS || size as usize != buffer.len() {
+        return None;
+    }
+    let [a, b, c, d, e, f, g, h, rest @ ..] = buffer;
+    Some(GUID::from_values(
+        u32::from_le_bytes([a, b, c, d]),
+        u16::from_le_bytes([e, f]),
+        u16::from_le_bytes([g, h]),
+        rest,
+    ))
+}
+
+/// Desktop the user is looking at. `IVirtualDesktopManager` has no way to
+/// ask, so this reads what Explorer keeps in the registry, per user on
+/// Windows 11 and per session on Windows 10.
+pub fn current_desktop_id() -> Option<GUID> {
+    read_desktop_id(&format!("{EXPLORER_KEY}\\VirtualDesktops")).or_else(|| {
+        let mut session = 0;
+        unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut session) }.ok()?;
+        read_desktop_id(&format!(
+            "{EXPLORER_KEY}\\SessionInfo\\{session}\\VirtualDesktops"
+        ))
+    })
 }
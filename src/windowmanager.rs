@@ -1,199 +1,677 @@
-use std::sync::OnceLock;
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{mpsc::Receiver, OnceLock},
+};
 
 use log::{debug, error, info};
 use windows::Win32::{
-    Foundation::{BOOL, HWND, LPARAM, LRESULT, RECT, TRUE, WPARAM},
+    Foundation::{HWND, LPARAM, WPARAM},
     UI::WindowsAndMessaging::{
-        GW_OWNER, HSHELL_WINDOWACTIVATED, HSHELL_WINDOWCREATED, HSHELL_WINDOWDESTROYED, WM_COMMAND,
-        WM_DISPLAYCHANGE, WM_USER, WS_CHILD, WS_DISABLED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
+        HSHELL_WINDOWACTIVATED, HSHELL_WINDOWCREATED, HSHELL_WINDOWDESTROYED, WM_COMMAND,
+        WM_DISPLAYCHANGE, WM_USER,
     },
 };
 
 use crate::{
-    config::Config, layout::Layout, win32, win32::virtualdesktop::VirtualDesktopManager,
-    window::Window,
+    backend::Backend,
+    config,
+    ipc::Envelope,
+    script::{ScriptLayout, ScriptSource},
+    trace::{Event, Record, Recorder},
+};
+use grout_core::{
+    config::{Config, Orientation},
+    desktops::{DesktopState, Desktops},
+    ipc::{Explanation, MonitorState, Request, Response, State, WindowState},
+    layout::{apply_gaps, LayoutParams, LayoutRegistry, Transform, BSP_LAYOUT, SCROLLING_LAYOUT},
+    minsize::{self, MinSize},
+    monitors::{self, Direction, Monitor},
+    strip::{Strip, DEFAULT_WIDTH},
+    window::WindowInfo,
+    Rect,
 };
-use grout_wm::{any, has_flag, Result, LOWORD};
+use grout_wm::{Result, LOWORD};
 
 pub const MSG_UNCLOAKED: u32 = WM_USER;
 pub const MSG_CLOAKED: u32 = WM_USER + 0x0001;
 pub const MSG_MINIMIZEEND: u32 = WM_USER + 0x0003;
 pub const MSG_MINIMIZESTART: u32 = WM_USER + 0x0004;
 pub const MSG_MOVESIZEEND: u32 = WM_USER + 0x0006;
+pub const MSG_REQUEST: u32 = WM_USER + 0x0007;
+
+pub const CMD_GROW_MAIN_RATIO: u32 = 0x0010;
+pub const CMD_SHRINK_MAIN_RATIO: u32 = 0x0011;
+pub const CMD_GROW_SPLIT_RATIO: u32 = 0x0012;
+pub const CMD_SHRINK_SPLIT_RATIO: u32 = 0x0013;
+pub const CMD_INCREASE_MASTERS: u32 = 0x0014;
+pub const CMD_DECREASE_MASTERS: u32 = 0x0015;
+pub const CMD_RESET_RATIOS: u32 = 0x0016;
+pub const CMD_NEXT_LAYOUT: u32 = 0x0017;
+pub const CMD_PREVIOUS_LAYOUT: u32 = 0x0018;
+pub const CMD_ROTATE_SPLIT: u32 = 0x0019;
+pub const CMD_BALANCE_SPLITS: u32 = 0x001A;
+pub const CMD_MIRROR_LAYOUT: u32 = 0x001B;
+pub const CMD_FLIP_LAYOUT: u32 = 0x001C;
+pub const CMD_TRANSPOSE_LAYOUT: u32 = 0x001D;
+pub const CMD_SEND_TO_MONITOR_LEFT: u32 = 0x001E;
+pub const CMD_SEND_TO_MONITOR_RIGHT: u32 = 0x001F;
+pub const CMD_FOCUS_NEXT: u32 = 0x0020;
+pub const CMD_SWAP_NEXT: u32 = 0x0021;
+pub const CMD_TOGGLE_FLOAT: u32 = 0x0022;
+pub const CMD_LAYOUT_BASE: u32 = 0x0100;
+pub const CMD_SEND_TO_MONITOR_BASE: u32 = 0x0200;
+pub const CMD_FOCUS_MONITOR_BASE: u32 = 0x0300;
+
+const RATIO_STEP: f32 = 0.05;
+const MIN_SIZE_TOLERANCE: i32 = 2;
 
 pub static SHELL_HOOK_ID: OnceLock<u32> = OnceLock::new();
 
 pub struct WindowManager {
-    managed_windows: Vec<Window>,
-    working_area: RECT,
+    backend: Box<dyn Backend>,
+    managed_windows: Vec<HWND>,
+    floating: Vec<HWND>,
+    monitors: Vec<Monitor>,
     config: Config,
-    virtual_desktop: VirtualDesktopManager,
-    layout: Layout,
+    desktops: Desktops<HWND>,
+    layouts: LayoutRegistry,
+    scripts: Vec<ScriptSource>,
+    rule_min_sizes: HashMap<isize, MinSize>,
+    learned_min_sizes: HashMap<isize, MinSize>,
+    focused: Option<HWND>,
     hwnd: HWND,
+    recorder: Option<Recorder>,
+    requests: Option<Receiver<Envelope>>,
+}
+
+fn load_layouts(config: &Config, scripts: &[ScriptSource]) -> Result<LayoutRegistry> {
+    let mut layouts = LayoutRegistry::default();
+    for custom in config.custom_layouts().inspect_err(|e| error!("{e}"))? {
+        layouts.register(Rc::new(custom))?;
+    }
+    for ScriptSource { name, source } in scripts {
+        let script = ScriptLayout::compile(name, source);
+        if let Err(e) = script.and_then(|script| Ok(layouts.register(Rc::new(script))?)) {
+            error!("{e}");
+        }
+    }
+    info!(
+        "Available layouts are {:?}",
+        layouts.names().collect::<Vec<_>>()
+    );
+    Ok(layouts)
 }
 
 impl WindowManager {
-    pub fn new(config: Config) -> Result<Self> {
+    pub fn new(
+        config: Config,
+        scripts: Vec<ScriptSource>,
+        backend: Box<dyn Backend>,
+    ) -> Result<Self> {
         info!("Create new instance of window manager");
-        let working_area = win32::get_working_area()?;
-        info!("Working area is {:?}", working_area);
-        let layout = match config.default_layout.as_ref().map(String::as_ref) {
-            Some("Monocle") => Layout::Monocle,
-            Some("Columns") => Layout::Columns,
-            Some("Focus") => Layout::Focus,
-            _ => Layout::Dwindle,
+        let monitors = backend.monitors()?;
+        info!("Monitors are {:?}", monitors);
+        let layouts = load_layouts(&config, &scripts)?;
+        let dwindle = layouts
+            .get("Dwindle")
+            .expect("Dwindle is a built-in layout");
+        let layout = match &config.default_layout {
+            Some(name) => layouts.get(name).unwrap_or_else(|| {
+                error!("Unknown layout {name}");
+                dwindle
+            }),
+            None => dwindle,
         };
-        Ok(WindowManager {
+        let strip = Strip::new(config.column_width.unwrap_or(DEFAULT_WIDTH));
+        let desktops = Desktops::new(DesktopState::new(layout, strip));
+        let wm = WindowManager {
+            backend,
             managed_windows: Default::default(),
-            working_area,
+            floating: Default::default(),
+            monitors,
             config,
-            virtual_desktop: VirtualDesktopManager::new()?,
-            layout,
+            desktops,
+            layouts,
+            scripts,
+            rule_min_sizes: Default::default(),
+            learned_min_sizes: Default::default(),
+            focused: None,
             hwnd: Default::default(),
-        })
+            recorder: None,
+            requests: None,
+        };
+        for rule in wm.config.auto_layout.iter().flatten() {
+            if wm.layouts.get(&rule.layout).is_none() {
+                error!("Unknown layout {} in auto_layout", rule.layout);
+            }
+        }
+        Ok(wm)
     }
 
-    fn get_window(&mut self, hwnd: HWND) -> Option<Window> {
-        self.managed_windows.iter().find(|w| w.0 == hwnd).copied()
+    fn is_managed(&self, hwnd: HWND) -> bool {
+        self.managed_windows.contains(&hwnd)
     }
 
-    pub fn manage(&mut self, hwnd: HWND) -> Option<Window> {
-        if let Some(w) = self.get_window(hwnd) {
-            info!("Window already managed {:?}", w);
-            Some(w)
+    pub fn manage(&mut self, hwnd: HWND) {
+        if self.is_managed(hwnd) {
+            info!("Window already managed {hwnd:?}");
         } else {
-            let w = Window::new(hwnd);
-            self.managed_windows.push(w);
-            info!("Manage new window {:?}", w);
-            Some(w)
+            self.managed_windows.push(hwnd);
+            let min_size = self.rule_min_size(&self.backend.window_info(hwnd));
+            self.rule_min_sizes.insert(hwnd.0, min_size);
+            // Filed right away so it splits the window focused before it
+            self.sync_desktops(&[]);
+            info!("Manage new window {hwnd:?}");
         }
     }
 
     fn is_manageable(&mut self, hwnd: HWND) -> bool {
-        if any!(self.managed_windows, hwnd) {
+        if self.is_managed(hwnd) {
             return true;
         }
-        let style = win32::get_window_style(hwnd);
-        let exstyle = win32::get_window_exstyle(hwnd);
-        let is_child = has_flag!(style, WS_CHILD.0);
-        let is_cloaked = win32::dwm::is_cloaked(hwnd);
-        let is_disabled = has_flag!(style, WS_DISABLED.0);
-        let is_tool = has_flag!(exstyle, WS_EX_TOOLWINDOW.0);
-        let is_visible = win32::is_window_visible(hwnd);
-        let no_activate = has_flag!(exstyle, WS_EX_NOACTIVATE.0);
-        let title = win32::get_window_text(hwnd);
-        let class_name = win32::get_window_classname(hwnd);
-        let process_name = win32::get_exe_filename(hwnd);
-        let title_len = win32::get_window_text_length(hwnd);
-        let owner = win32::get_window(hwnd, GW_OWNER);
-        if title_len == 0 || is_disabled || process_name.is_none() {
-            return false;
-        }
-        if let Some(titles) = &self.config.windows_ui_core_corewindow {
-            if class_name.contains("Windows.UI.Core.CoreWindow")
-                && titles.iter().any(|t| title.contains(t))
-            {
-                return false;
-            }
-        }
-        if let Some(classes) = &self.config.class_names {
-            if classes.iter().any(|cn| class_name.contains(cn)) {
-                return false;
-            }
-        }
-        if let Some(processes) = &self.config.process_names {
-            if let Some(process_name) = process_name {
-                if processes.iter().any(|p| process_name.contains(p)) {
-                    return false;
-                }
-            }
-        }
-        let is_app_window = is_visible && !no_activate && !is_child;
-        let is_alt_tab_window = !(is_tool || owner.0 != 0);
-        let retval = !is_cloaked && is_app_window && is_alt_tab_window;
-        info!("Is manageable {retval} - {title}");
+        let info = self.backend.window_info(hwnd);
+        let retval = info.rejections(&self.config).is_empty();
+        info!("Is manageable {retval} - {}", info.title);
         retval
     }
 
     fn unmanage(&mut self, hwnd: HWND) {
-        if !any!(self.managed_windows, hwnd) {
+        if !self.is_managed(hwnd) {
             return;
         }
-        let is_on_desktop = self
-            .virtual_desktop
-            .is_window_on_current_desktop(hwnd)
-            .unwrap_or(false);
-        if is_on_desktop {
-            self.managed_windows.retain(|w| w.0 != hwnd);
+        if self.backend.is_on_current_desktop(hwnd) {
+            self.managed_windows.retain(|&w| w != hwnd);
+            self.floating.retain(|&w| w != hwnd);
+            self.rule_min_sizes.remove(&hwnd.0);
+            self.learned_min_sizes.remove(&hwnd.0);
+            if self.focused == Some(hwnd) {
+                self.focused = None;
+            }
+        }
+    }
+
+    fn is_on_screen(&self, hwnd: HWND) -> bool {
+        !self.backend.is_iconic(hwnd) && self.backend.is_on_current_desktop(hwnd)
+    }
+
+    fn monitor_of(&self, hwnd: HWND) -> usize {
+        if self.backend.is_iconic(hwnd) {
+            // Minimized windows are parked off screen, keep them where they were
+            self.desktops.monitor_of(hwnd).unwrap_or(0)
+        } else {
+            monitors::monitor_for(&self.monitors, self.backend.window_rect(hwnd)).unwrap_or(0)
+        }
+    }
+
+    fn active_monitor(&self) -> usize {
+        self.focused
+            .and_then(|hwnd| self.desktops.monitor_of(hwnd))
+            .unwrap_or(0)
+    }
+
+    fn desktop(&self) -> &DesktopState<HWND> {
+        self.desktops.current(self.active_monitor())
+    }
+
+    fn desktop_mut(&mut self) -> &mut DesktopState<HWND> {
+        self.desktops.current_mut(self.active_monitor())
+    }
+
+    fn is_bsp(&self) -> bool {
+        self.desktop().layout.name() == BSP_LAYOUT
+    }
+
+    fn is_scrolling(&self) -> bool {
+        self.desktop().layout.name() == SCROLLING_LAYOUT
+    }
+
+    fn windows_on_screen(&self) -> Vec<HWND> {
+        (0..self.monitors.len())
+            .flat_map(|monitor| self.windows_on_monitor(monitor))
+            .collect()
+    }
+
+    fn windows_on_monitor(&self, monitor: usize) -> Vec<HWND> {
+        self.desktops
+            .current(monitor)
+            .windows
+            .iter()
+            .copied()
+            .filter(|&hwnd| self.is_on_screen(hwnd) && !self.floating.contains(&hwnd))
+            .collect()
+    }
+
+    /// Tiled window after the focused one on the active monitor, wrapping
+    /// around, or the first one when none of them has focus.
+    fn next_window(&self) -> Option<HWND> {
+        let windows = self.windows_on_monitor(self.active_monitor());
+        let next = match self
+            .focused
+            .and_then(|focused| windows.iter().position(|&w| w == focused))
+        {
+            Some(idx) => windows.get((idx + 1) % windows.len()),
+            None => windows.first(),
+        };
+        next.copied()
+    }
+
+    fn default_layout_params(&self) -> LayoutParams {
+        LayoutParams {
+            masters: self.config.master_count.unwrap_or(1),
+            main_ratio: self.config.master_ratio.unwrap_or(0.5),
+            stack_balance: self.config.stack_balance.unwrap_or(0.5),
+            ..Default::default()
         }
     }
 
-    pub fn arrange(&self) {
-        let windows_on_screen: Vec<Window> = self
+    fn layout_params_mut(&mut self) -> &mut LayoutParams {
+        let default_params = self.default_layout_params();
+        let desktop = self.desktop_mut();
+        desktop
+            .layout_params
+            .entry(desktop.layout.name().to_owned())
+            .or_insert(default_params)
+    }
+
+    fn transform_mut(&mut self) -> &mut Transform {
+        let default_transform = self.config.transform_for(self.desktop().layout.name());
+        let desktop = self.desktop_mut();
+        desktop
+            .transforms
+            .entry(desktop.layout.name().to_owned())
+            .or_insert(default_transform)
+    }
+
+    fn focused_split(&self) -> Option<usize> {
+        let foreground = self.backend.foreground_window();
+        self.windows_on_monitor(self.active_monitor())
+            .iter()
+            .position(|&hwnd| hwnd == foreground)
+            .map(|idx| idx.saturating_sub(1))
+    }
+
+    /// Monitor to file the window under. Windows stay on the one they were
+    /// filed under, scrolled off columns would otherwise land on the next
+    /// one, unless they're new or being refiled.
+    fn filing_monitor(&self, hwnd: HWND, refile: &[HWND]) -> usize {
+        self.desktops
+            .filed_monitor(hwnd)
+            .filter(|&monitor| monitor < self.monitors.len() && !refile.contains(&hwnd))
+            .unwrap_or_else(|| self.monitor_of(hwnd))
+    }
+
+    fn sync_desktops(&mut self, refile: &[HWND]) {
+        let managed: Vec<(HWND, usize)> = self
             .managed_windows
-            .clone()
+            .iter()
+            .map(|&hwnd| (hwnd, self.filing_monitor(hwnd, refile)))
+            .collect();
+        let split_at = self.focused.filter(|&f| self.is_on_screen(f));
+        self.desktops.sync(&*self.backend, &managed, split_at);
+    }
+
+    pub fn arrange(&mut self) {
+        self.sync_desktops(&[]);
+        for monitor in 0..self.monitors.len() {
+            self.arrange_monitor(monitor);
+        }
+    }
+
+    fn arrange_monitor(&mut self, monitor: usize) {
+        self.apply_auto_layout(monitor);
+        if self.place_windows(monitor) {
+            debug!("Learned new minimum sizes, arranging again");
+            self.place_windows(monitor);
+        }
+    }
+
+    // Rules only kick in when the window count or screen shape changes, so a
+    // layout picked by hand stays until then
+    fn apply_auto_layout(&mut self, monitor: usize) {
+        let state = (
+            self.windows_on_monitor(monitor).len(),
+            Orientation::of(self.monitors[monitor].work_area),
+        );
+        let desktop = self.desktops.current_mut(monitor);
+        if desktop.auto_layout_state == Some(state) {
+            return;
+        }
+        desktop.auto_layout_state = Some(state);
+        let layout = self
+            .config
+            .auto_layout_for(state.0, state.1)
+            .and_then(|name| self.layouts.get(name));
+        if let Some(layout) = layout.filter(|layout| layout.name() != desktop.layout.name()) {
+            info!(
+                "Automatically switch to layout {} on monitor {monitor}",
+                layout.name()
+            );
+            desktop.layout = layout;
+        }
+    }
+
+    fn rule_min_size(&self, info: &WindowInfo) -> MinSize {
+        self.config.min_size_for(
+            &info.class_name,
+            info.process_name.as_deref().unwrap_or_default(),
+            &info.title,
+        )
+    }
+
+    fn min_size(&self, hwnd: HWND) -> MinSize {
+        let rules = self.rule_min_sizes.get(&hwnd.0).copied();
+        let learned = self.learned_min_sizes.get(&hwnd.0).copied();
+        rules.unwrap_or_default().max(learned.unwrap_or_default())
+    }
+
+    fn place_windows(&mut self, monitor: usize) -> bool {
+        let working_area = self.monitors[monitor].work_area;
+        let windows_on_screen = self.windows_on_monitor(monitor);
+        let layout_name = self.desktops.current(monitor).layout.name().to_owned();
+        let is_scrolling = layout_name == SCROLLING_LAYOUT;
+        let (windows, rects): (Vec<HWND>, Vec<Rect>) = if layout_name == BSP_LAYOUT {
+            let mut view = self.desktops.current(monitor).bsp.clone();
+            view.retain(|hwnd| windows_on_screen.contains(&hwnd));
+            view.rects(working_area).into_iter().unzip()
+        } else if is_scrolling {
+            let windows = windows_on_screen;
+            let focused = self.focused;
+            let strip = &mut self.desktops.current_mut(monitor).strip;
+            if let Some(focused) = focused {
+                let viewport = working_area.right - working_area.left;
+                strip.scroll_to(&windows, focused, viewport);
+            }
+            let rects = strip.rects(&windows, working_area);
+            (windows, rects)
+        } else {
+            let desktop = self.desktops.current(monitor);
+            let params = desktop
+                .layout_params
+                .get(desktop.layout.name())
+                .cloned()
+                .unwrap_or_else(|| self.default_layout_params());
+            let transform = desktop
+                .transforms
+                .get(desktop.layout.name())
+                .copied()
+                .unwrap_or_else(|| self.config.transform_for(desktop.layout.name()));
+            let rects = transform.arrange(
+                &*desktop.layout,
+                working_area,
+                windows_on_screen.len(),
+                &params,
+            );
+            (windows_on_screen, rects)
+        };
+        let (inner, outer) = self.config.gaps_for(&layout_name);
+        let rects = if is_scrolling {
+            rects
+        } else {
+            // Gaps are taken out of the rects afterwards, so leave room for them
+            let slack = 2 * outer.max(inner - inner / 2);
+            let min_sizes: Vec<MinSize> = windows
+                .iter()
+                .map(|&hwnd| self.min_size(hwnd))
+                .map(|min| MinSize {
+                    width: min.width + slack * (min.width > 0) as i32,
+                    height: min.height + slack * (min.height > 0) as i32,
+                })
+                .collect();
+            minsize::solve(working_area, &rects, &min_sizes)
+        };
+        let ds = apply_gaps(working_area, rects, inner, outer);
+        let positions: Vec<(HWND, Rect)> =
+            windows.iter().copied().zip(ds.iter().copied()).collect();
+        self.backend.set_window_positions(&positions);
+        self.backend.invalidate_thumbnail(self.hwnd);
+        let mut learned_new = false;
+        for (&hwnd, d) in windows.iter().zip(ds.iter()) {
+            let frame = self.backend.visible_frame(hwnd);
+            let actual = MinSize {
+                width: frame.right - frame.left,
+                height: frame.bottom - frame.top,
+            };
+            let placed = MinSize {
+                width: d.right - d.left + MIN_SIZE_TOLERANCE,
+                height: d.bottom - d.top + MIN_SIZE_TOLERANCE,
+            };
+            let learned = self.learned_min_sizes.entry(hwnd.0).or_default();
+            let mut grown = *learned;
+            // Taking less than it was thought to need means whatever held it
+            // back has passed, a restore or a resize still in flight
+            if actual.width > placed.width {
+                grown.width = grown.width.max(actual.width);
+            } else if actual.width < learned.width {
+                grown.width = 0;
+            }
+            if actual.height > placed.height {
+                grown.height = grown.height.max(actual.height);
+            } else if actual.height < learned.height {
+                grown.height = 0;
+            }
+            if grown.width > learned.width || grown.height > learned.height {
+                info!("Window {hwnd:?} refuses to shrink below {grown:?}");
+                learned_new = true;
+            }
+            *learned = grown;
+        }
+        learned_new
+    }
+
+    /// Starts writing every event to `recorder`, beginning with the config,
+    /// the layout scripts and the windows open right now.
+    #[cfg(any(windows, test))]
+    pub fn record_to(&mut self, recorder: Recorder) {
+        let visible: Vec<HWND> = self
+            .backend
+            .windows()
             .into_iter()
-            .filter(|w| !w.is_iconic())
-            .filter(|w| {
-                self.virtual_desktop
-                    .is_window_on_current_desktop(w.0)
-                    .unwrap_or(false)
-            })
+            .filter(|&hwnd| self.backend.window_info(hwnd).is_visible)
             .collect();
-        let number_of_windows = windows_on_screen.len();
-        let ds = self.layout.arrange(self.working_area, number_of_windows);
-        if let Ok(mut hdwp) = win32::begin_defer_window_pos(number_of_windows) {
-            for (w, d) in windows_on_screen.iter().zip(ds.iter()) {
-                if let Ok(res) = win32::defer_window_pos(hdwp, w.0, *d) {
-                    hdwp = res;
+        let record = Record {
+            config: Some(self.config.clone()),
+            scripts: self.scripts.clone(),
+            ..Record::capture(&*self.backend, Event::Start, HWND(0), visible)
+        };
+        self.recorder.insert(recorder).write(&record);
+    }
+
+    fn record(&mut self, event: Event, handle: HWND) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+        // Layout ids are only good for this session's registry
+        let event = match event {
+            Event::Command(id) if (CMD_LAYOUT_BASE..CMD_SEND_TO_MONITOR_BASE).contains(&id) => {
+                match self.layouts.get_index((id - CMD_LAYOUT_BASE) as usize) {
+                    Some(layout) => Event::SetLayout(layout.name().to_owned()),
+                    None => Event::Command(id),
                 }
             }
-            win32::end_defer_window_pos(hdwp);
+            event => event,
+        };
+        let mut windows = self.managed_windows.clone();
+        let handle = if event.has_window() { handle } else { HWND(0) };
+        if handle.0 != 0 && !windows.contains(&handle) {
+            windows.push(handle);
         }
-        let _ = win32::dwm::invalidate_iconic_bitmaps(self.hwnd);
+        let (config, scripts) = match event {
+            Event::ConfigReloaded => (Some(self.config.clone()), self.scripts.clone()),
+            _ => (None, vec![]),
+        };
+        recorder.write(&Record {
+            config,
+            scripts,
+            ..Record::capture(&*self.backend, event, handle, windows)
+        });
     }
 
-    pub fn message_loop(
-        &mut self,
-        hwnd: HWND,
-        msg: u32,
-        wparam: WPARAM,
-        lparam: LPARAM,
-    ) -> LRESULT {
+    /// Handles a message sent to the app window, returning false for
+    /// messages that aren't the window manager's.
+    pub fn message_loop(&mut self, msg: u32, wparam: WPARAM, lparam: LPARAM) -> bool {
         let handle = HWND(lparam.0);
-        let managed_window = self.get_window(handle);
+        let is_managed = self.is_managed(handle);
         let wmsg = LOWORD!(wparam.0) as u32;
         let shell_hook_id = SHELL_HOOK_ID.get().unwrap_or(&0);
+        if let Some(event) = Event::from_message(msg, wparam, *shell_hook_id) {
+            self.record(event, handle);
+        }
         match (msg, wmsg) {
             (WM_DISPLAYCHANGE, _) => {
-                self.working_area = win32::get_working_area().unwrap();
+                match self.backend.monitors() {
+                    Ok(monitors) => {
+                        info!("Monitors changed to {:?}", monitors);
+                        self.monitors = monitors;
+                        // Scaling may have changed with them
+                        self.learned_min_sizes.clear();
+                    }
+                    Err(e) => error!("Can not get monitors: {e}"),
+                }
+                self.sync_desktops(&self.managed_windows.clone());
                 self.arrange();
             }
-            (WM_COMMAND, 0) => {
-                self.set_layout(Layout::Dwindle);
+            (WM_COMMAND, CMD_GROW_MAIN_RATIO | CMD_GROW_SPLIT_RATIO) if self.is_scrolling() => {
+                if let Some(focused) = self.focused {
+                    self.desktop_mut().strip.adjust_width(focused, RATIO_STEP);
+                    self.arrange();
+                }
+            }
+            (WM_COMMAND, CMD_SHRINK_MAIN_RATIO | CMD_SHRINK_SPLIT_RATIO) if self.is_scrolling() => {
+                if let Some(focused) = self.focused {
+                    self.desktop_mut().strip.adjust_width(focused, -RATIO_STEP);
+                    self.arrange();
+                }
+            }
+            (WM_COMMAND, CMD_GROW_MAIN_RATIO) => {
+                self.layout_params_mut().adjust_main_ratio(RATIO_STEP);
+                self.arrange();
+            }
+            (WM_COMMAND, CMD_SHRINK_MAIN_RATIO) => {
+                self.layout_params_mut().adjust_main_ratio(-RATIO_STEP);
                 self.arrange();
             }
-            (WM_COMMAND, 1) => {
-                self.set_layout(Layout::Monocle);
+            (WM_COMMAND, CMD_GROW_SPLIT_RATIO) if self.is_bsp() => {
+                if let Some(focused) = self.focused {
+                    self.desktop_mut().bsp.adjust_ratio(focused, RATIO_STEP);
+                    self.arrange();
+                }
+            }
+            (WM_COMMAND, CMD_SHRINK_SPLIT_RATIO) if self.is_bsp() => {
+                if let Some(focused) = self.focused {
+                    self.desktop_mut().bsp.adjust_ratio(focused, -RATIO_STEP);
+                    self.arrange();
+                }
+            }
+            (WM_COMMAND, CMD_GROW_SPLIT_RATIO) => {
+                if let Some(split) = self.focused_split() {
+                    self.layout_params_mut()
+                        .adjust_split_ratio(split, RATIO_STEP);
+                    self.arrange();
+                }
+            }
+            (WM_COMMAND, CMD_SHRINK_SPLIT_RATIO) => {
+                if let Some(split) = self.focused_split() {
+                    self.layout_params_mut()
+                        .adjust_split_ratio(split, -RATIO_STEP);
+                    self.arrange();
+                }
+            }
+            (WM_COMMAND, CMD_INCREASE_MASTERS) => {
+                self.layout_params_mut().adjust_masters(1);
                 self.arrange();
             }
-            (WM_COMMAND, 2) => {
-                self.set_layout(Layout::Columns);
+            (WM_COMMAND, CMD_DECREASE_MASTERS) => {
+                self.layout_params_mut().adjust_masters(-1);
                 self.arrange();
             }
-            (WM_COMMAND, 3) => {
-                self.set_layout(Layout::Focus);
+            (WM_COMMAND, CMD_RESET_RATIOS) => {
+                let desktop = self.desktop_mut();
+                desktop.layout_params.remove(desktop.layout.name());
                 self.arrange();
             }
+            (WM_COMMAND, CMD_ROTATE_SPLIT) if self.is_bsp() => {
+                if let Some(focused) = self.focused {
+                    self.desktop_mut().bsp.rotate(focused);
+                    self.arrange();
+                }
+            }
+            (WM_COMMAND, CMD_BALANCE_SPLITS) if self.is_bsp() => {
+                self.desktop_mut().bsp.balance();
+                self.arrange();
+            }
+            (WM_COMMAND, CMD_MIRROR_LAYOUT) => {
+                let transform = self.transform_mut();
+                transform.mirror = !transform.mirror;
+                self.arrange();
+            }
+            (WM_COMMAND, CMD_FLIP_LAYOUT) => {
+                let transform = self.transform_mut();
+                transform.flip = !transform.flip;
+                self.arrange();
+            }
+            (WM_COMMAND, CMD_TRANSPOSE_LAYOUT) => {
+                let transform = self.transform_mut();
+                transform.transpose = !transform.transpose;
+                self.arrange();
+            }
+            (WM_COMMAND, CMD_NEXT_LAYOUT) => {
+                self.next_layout();
+                self.arrange();
+            }
+            (WM_COMMAND, CMD_PREVIOUS_LAYOUT) => {
+                self.previous_layout();
+                self.arrange();
+            }
+            (WM_COMMAND, CMD_SEND_TO_MONITOR_LEFT) => {
+                self.send_focused_to(Direction::Left);
+            }
+            (WM_COMMAND, CMD_SEND_TO_MONITOR_RIGHT) => {
+                self.send_focused_to(Direction::Right);
+            }
+            (WM_COMMAND, CMD_FOCUS_NEXT) => {
+                self.focus_next();
+            }
+            (WM_COMMAND, CMD_SWAP_NEXT) => {
+                self.swap_next();
+            }
+            (WM_COMMAND, CMD_TOGGLE_FLOAT) => {
+                self.toggle_float();
+            }
+            (WM_COMMAND, id) if id >= CMD_FOCUS_MONITOR_BASE => {
+                self.focus_monitor((id - CMD_FOCUS_MONITOR_BASE) as usize);
+            }
+            (WM_COMMAND, id) if id >= CMD_SEND_TO_MONITOR_BASE => {
+                self.send_focused_to_monitor((id - CMD_SEND_TO_MONITOR_BASE) as usize);
+            }
+            (WM_COMMAND, id) if id >= CMD_LAYOUT_BASE => {
+                if let Some(layout) = self.layouts.get_index((id - CMD_LAYOUT_BASE) as usize) {
+                    self.desktop_mut().layout = layout;
+                    self.arrange();
+                }
+            }
+            (MSG_REQUEST, _) => {
+                let pending: Vec<Envelope> = self
+                    .requests
+                    .as_ref()
+                    .map(|requests| requests.try_iter().collect())
+                    .unwrap_or_default();
+                for (request, reply) in pending {
+                    debug!("Request {request:?}");
+                    let response = self.handle_request(request);
+                    // The client may have hung up in the meantime
+                    let _ = reply.send(response);
+                }
+            }
             (MSG_CLOAKED, _) => {
-                if managed_window.is_some() {
-                    debug!("Cloaked: {managed_window:#?}");
+                if is_managed {
+                    debug!("Cloaked: {handle:?}");
                     self.unmanage(handle);
                     self.arrange();
                 }
             }
             (MSG_UNCLOAKED, _) => {
-                if managed_window.is_none() && self.is_manageable(handle) {
+                if !is_managed && self.is_manageable(handle) {
                     debug!("Uncloaked: {handle:?}");
                     self.manage(handle);
                     self.arrange();
@@ -203,26 +681,22 @@ impl WindowManager {
                 self.arrange();
             }
             (MSG_MOVESIZEEND, _) => {
-                if let Some(window) = managed_window {
-                    let mouse_pos = win32::get_cursor_pos();
-                    let landed_on_window_opt = self.managed_windows.iter().position(|&w| {
-                        let is_on_desktop = self
-                            .virtual_desktop
-                            .is_window_on_current_desktop(w.0)
-                            .unwrap_or(false);
-                        is_on_desktop
-                            && w.0 != window.0
-                            && win32::point_in_rect(w.position(), mouse_pos)
-                    });
-                    if let Some(landed_on_idx) = landed_on_window_opt {
-                        let window_idx = self
-                            .managed_windows
-                            .iter()
-                            .position(|&w| w.0 == window.0)
-                            .unwrap();
-                        if window_idx != landed_on_idx {
-                            let window = self.managed_windows.remove(window_idx);
-                            self.managed_windows.insert(landed_on_idx, window);
+                if is_managed {
+                    // The user may have resized it, so find out again what it takes
+                    self.learned_min_sizes.remove(&handle.0);
+                    let mouse_pos = self.backend.cursor_pos();
+                    self.sync_desktops(&[handle]);
+                    let landed_on_window_opt = self
+                        .windows_on_screen()
+                        .into_iter()
+                        .find(|&w| w != handle && self.backend.window_rect(w).contains(mouse_pos));
+                    if let Some(landed_on) = landed_on_window_opt {
+                        let monitor = self.desktops.monitor_of(landed_on).unwrap_or(0);
+                        let desktop = self.desktops.current_mut(monitor);
+                        if desktop.layout.name() == BSP_LAYOUT {
+                            desktop.bsp.swap(handle, landed_on);
+                        } else {
+                            desktop.move_window(handle, landed_on);
                         }
                     }
                     self.arrange();
@@ -230,7 +704,7 @@ impl WindowManager {
             }
             (id, HSHELL_WINDOWCREATED) if id == *shell_hook_id => {
                 debug!("{handle:?} is created");
-                if managed_window.is_none() && self.is_manageable(handle) {
+                if !is_managed && self.is_manageable(handle) {
                     debug!("{handle:?} is created");
                     self.manage(handle);
                     self.arrange();
@@ -238,7 +712,7 @@ impl WindowManager {
             }
             (id, HSHELL_WINDOWDESTROYED) if id == *shell_hook_id => {
                 debug!("{handle:?} is destroyed");
-                if managed_window.is_some() {
+                if is_managed {
                     debug!("{handle:?} is destroyed");
                     self.unmanage(handle);
                     self.arrange();
@@ -246,41 +720,577 @@ impl WindowManager {
             }
             (id, HSHELL_WINDOWACTIVATED) if id == *shell_hook_id => {
                 debug!("{handle:?} is activated");
-                if managed_window.is_none() && self.is_manageable(handle) {
+                if !is_managed && self.is_manageable(handle) {
                     info!("Activate {handle:?}");
                     self.manage(handle);
                 }
+                if self.is_managed(handle) {
+                    self.focused = Some(handle);
+                }
                 self.arrange();
             }
-            _ => return win32::def_window_proc(hwnd, msg, wparam, lparam),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn enum_windows(&mut self) -> &mut Self {
+        for hwnd in self.backend.windows() {
+            if self.is_manageable(hwnd) {
+                self.manage(hwnd);
+            }
+        }
+        self.arrange();
+        self
+    }
+
+    pub fn send_focused_to(&mut self, direction: Direction) {
+        if let Some(monitor) = monitors::neighbour(&self.monitors, self.active_monitor(), direction)
+        {
+            self.send_focused_to_monitor(monitor);
+        }
+    }
+
+    pub fn send_focused_to_monitor(&mut self, monitor: usize) {
+        let Some(focused) = self.focused else {
+            return;
+        };
+        let from = self.active_monitor();
+        if monitor >= self.monitors.len() || monitor == from {
+            return;
+        }
+        info!("Send {focused:?} from monitor {from} to {monitor}");
+        self.desktops.move_to_monitor(focused, monitor);
+        self.arrange_monitor(from);
+        self.arrange_monitor(monitor);
+    }
+
+    pub fn focus_monitor(&mut self, monitor: usize) {
+        if let Some(&hwnd) = self.windows_on_monitor(monitor).first() {
+            if self.backend.set_foreground_window(hwnd) {
+                self.focused = Some(hwnd);
+            } else {
+                error!("Can not focus {hwnd:?} on monitor {monitor}");
+            }
+        }
+    }
+
+    pub fn focus_next(&mut self) {
+        if let Some(next) = self.next_window() {
+            if self.backend.set_foreground_window(next) {
+                self.focused = Some(next);
+            } else {
+                error!("Can not focus {next:?}");
+            }
+        }
+    }
+
+    pub fn swap_next(&mut self) {
+        let Some(focused) = self
+            .focused
+            .filter(|focused| self.windows_on_screen().contains(focused))
+        else {
+            return;
+        };
+        let Some(next) = self.next_window().filter(|&next| next != focused) else {
+            return;
+        };
+        if self.is_bsp() {
+            self.desktop_mut().bsp.swap(focused, next);
+        } else {
+            self.desktop_mut().swap_windows(focused, next);
         }
-        LRESULT(0)
+        self.arrange();
     }
 
-    pub fn enum_windows(&mut self) -> Result<&mut Self> {
-        let self_ptr = LPARAM(self as *mut Self as isize);
-        if win32::enum_windows(Some(Self::scan), self_ptr) {
-            self.arrange();
-            Ok(self)
+    pub fn toggle_float(&mut self) {
+        let Some(focused) = self.focused else {
+            return;
+        };
+        if self.floating.contains(&focused) {
+            info!("Tile {focused:?}");
+            self.floating.retain(|&w| w != focused);
         } else {
-            error!("Can not enum windows");
-            Err("Can not enum windows".into())
+            info!("Float {focused:?}");
+            self.floating.push(focused);
+        }
+        self.arrange();
+    }
+
+    /// Swaps in a new config and layout scripts. Desktops keep their
+    /// layouts, picking up new definitions of them, and auto layout rules get
+    /// another look.
+    pub fn reload_config(&mut self, config: Config, scripts: Vec<ScriptSource>) -> Result<()> {
+        let layouts = load_layouts(&config, &scripts)?;
+        for state in self.desktops.states_mut() {
+            if let Some(layout) = layouts.get(state.layout.name()) {
+                state.layout = layout;
+            }
+            state.auto_layout_state = None;
+        }
+        self.layouts = layouts;
+        self.config = config;
+        self.scripts = scripts;
+        self.record(Event::ConfigReloaded, HWND(0));
+        for &hwnd in &self.managed_windows {
+            let min_size = self.rule_min_size(&self.backend.window_info(hwnd));
+            self.rule_min_sizes.insert(hwnd.0, min_size);
+        }
+        info!("Config reloaded");
+        self.arrange();
+        Ok(())
+    }
+
+    pub fn state(&self) -> State {
+        let monitors =
+            self.monitors
+                .iter()
+                .enumerate()
+                .map(|(monitor, &Monitor { work_area, .. })| {
+                    let tiled = self.windows_on_monitor(monitor).into_iter();
+                    let floating = self.floating.iter().copied().filter(|&hwnd| {
+                        self.is_on_screen(hwnd) && self.monitor_of(hwnd) == monitor
+                    });
+                    let windows = tiled
+                        .map(|hwnd| (hwnd, false))
+                        .chain(floating.map(|hwnd| (hwnd, true)))
+                        .map(|(hwnd, floating)| {
+                            let info = self.backend.window_info(hwnd);
+                            WindowState {
+                                hwnd: hwnd.0,
+                                title: info.title,
+                                class_name: info.class_name,
+                                process_name: info.process_name,
+                                rect: self.backend.window_rect(hwnd),
+                                floating,
+                            }
+                        })
+                        .collect();
+                    MonitorState {
+                        layout: self.desktops.current(monitor).layout.name().to_owned(),
+                        work_area,
+                        windows,
+                    }
+                })
+                .collect();
+        State {
+            layouts: self.layouts.names().map(str::to_owned).collect(),
+            focused: self.focused.map(|hwnd| hwnd.0),
+            monitors,
         }
     }
 
-    extern "system" fn scan(hwnd: HWND, lparam: LPARAM) -> BOOL {
-        let wm: &mut Self = unsafe { &mut *(lparam.0 as *mut Self) };
-        if wm.is_manageable(hwnd) {
-            wm.manage(hwnd);
+    pub fn explain(&self, hwnd: HWND) -> Explanation {
+        let info = self.backend.window_info(hwnd);
+        let managed = self.is_managed(hwnd);
+        let floating = self.floating.contains(&hwnd);
+        let monitor = (0..self.monitors.len())
+            .find(|&monitor| self.windows_on_monitor(monitor).contains(&hwnd));
+        let rejections = if managed {
+            vec![]
+        } else {
+            info.rejections(&self.config)
+                .into_iter()
+                .map(str::to_owned)
+                .collect()
+        };
+        Explanation {
+            hwnd: hwnd.0,
+            managed,
+            floating,
+            minimized: self.backend.is_iconic(hwnd),
+            on_current_desktop: self.backend.is_on_current_desktop(hwnd),
+            monitor,
+            rejections,
+            title: info.title,
+            class_name: info.class_name,
+            process_name: info.process_name,
         }
-        TRUE
     }
 
-    pub fn set_layout(&mut self, layout: Layout) {
-        self.layout = layout;
+    /// Carries out a client's request. Commands go through `message_loop`
+    /// as `WM_COMMAND`, the same way the thumb buttons do.
+    pub fn handle_request(&mut self, request: Request) -> Response {
+        let command = match request {
+            Request::SetLayout { layout } => match self.layout_command(&layout) {
+                Some(command) => command,
+                None => return Response::error(format!("Unknown layout {layout}")),
+            },
+            Request::Swap => CMD_SWAP_NEXT,
+            Request::FocusNext => CMD_FOCUS_NEXT,
+            Request::ToggleFloat => CMD_TOGGLE_FLOAT,
+            Request::ReloadConfig => {
+                let reloaded = config::load()
+                    .and_then(|config| self.reload_config(config, config::load_scripts()));
+                return match reloaded {
+                    Ok(()) => Response::Ok,
+                    Err(e) => Response::error(e.to_string()),
+                };
+            }
+            Request::QueryState => return Response::State(self.state()),
+            Request::Explain { hwnd } => return Response::Explanation(self.explain(HWND(hwnd))),
+        };
+        self.message_loop(WM_COMMAND, WPARAM(command as usize), LPARAM(0));
+        Response::Ok
+    }
+
+    /// Command switching to the layout called `name`, ignoring case.
+    pub fn layout_command(&self, name: &str) -> Option<u32> {
+        self.layouts
+            .names()
+            .position(|layout| layout.eq_ignore_ascii_case(name))
+            .map(|idx| CMD_LAYOUT_BASE + idx as u32)
     }
 
+    #[cfg(any(windows, test))]
+    pub fn set_requests(&mut self, requests: Receiver<Envelope>) {
+        self.requests = Some(requests);
+    }
+
+    pub fn next_layout(&mut self) {
+        if let Some(layout) = self.layouts.next(self.desktop().layout.name()) {
+            self.desktop_mut().layout = layout;
+        }
+    }
+
+    pub fn previous_layout(&mut self) {
+        if let Some(layout) = self.layouts.previous(self.desktop().layout.name()) {
+            self.desktop_mut().layout = layout;
+        }
+    }
+
+    /// Layout and tiled windows of each monitor on the current desktop.
+    pub fn monitor_layouts(&self) -> Vec<(String, Vec<HWND>)> {
+        (0..self.monitors.len())
+            .map(|monitor| {
+                let layout = self.desktops.current(monitor).layout.name().to_owned();
+                (layout, self.windows_on_monitor(monitor))
+            })
+            .collect()
+    }
+
+    #[cfg(windows)]
+    pub fn layouts(&self) -> &LayoutRegistry {
+        &self.layouts
+    }
+
+    #[cfg(windows)]
     pub fn set_hwnd(&mut self, hwnd: HWND) {
         self.hwnd = hwnd;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use windows::Win32::UI::WindowsAndMessaging::WS_EX_TOOLWINDOW;
+
+    use super::*;
+    use crate::backend::fake::FakeBackend;
+    use grout_core::Point;
+
+    const SHELL_HOOK: u32 = 0xC000;
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> Rect {
+        Rect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn monitor(area: Rect) -> Monitor {
+        Monitor {
+            area,
+            work_area: area,
+        }
+    }
+
+    fn window_manager(backend: &FakeBackend) -> WindowManager {
+        SHELL_HOOK_ID.get_or_init(|| SHELL_HOOK);
+        let config = serde_yaml::from_str(include_str!("../default.yaml")).unwrap();
+        let mut wm = WindowManager::new(config, vec![], Box::new(backend.clone())).unwrap();
+        wm.enum_windows();
+        wm
+    }
+
+    fn send(wm: &mut WindowManager, msg: u32, wparam: u32, hwnd: HWND) -> bool {
+        wm.message_loop(msg, WPARAM(wparam as usize), LPARAM(hwnd.0))
+    }
+
+    fn single_monitor() -> FakeBackend {
+        FakeBackend::new(vec![monitor(rect(0, 0, 1000, 600))])
+    }
+
+    #[test]
+    fn existing_windows_are_tiled() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(50, 50, 300, 300));
+        window_manager(&backend);
+        assert_eq!(backend.rect(a), rect(0, 0, 500, 600));
+        assert_eq!(backend.rect(b), rect(500, 0, 1000, 600));
+    }
+
+    #[test]
+    fn tool_and_untitled_windows_are_left_alone() {
+        let backend = single_monitor();
+        let app = backend.open(1, "app", rect(10, 10, 200, 200));
+        let tool = backend.open(2, "tool", rect(10, 10, 200, 200));
+        backend.update(tool, |w| w.info.exstyle = WS_EX_TOOLWINDOW.0);
+        let untitled = backend.open(3, "", rect(10, 10, 200, 200));
+        window_manager(&backend);
+        assert_eq!(backend.rect(app), rect(0, 0, 1000, 600));
+        assert_eq!(backend.rect(tool), rect(10, 10, 200, 200));
+        assert_eq!(backend.rect(untitled), rect(10, 10, 200, 200));
+    }
+
+    #[test]
+    fn created_and_destroyed_windows_are_retiled() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let mut wm = window_manager(&backend);
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWCREATED, b));
+        assert_eq!(backend.rect(a), rect(0, 0, 500, 600));
+        assert_eq!(backend.rect(b), rect(500, 0, 1000, 600));
+        assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWDESTROYED, b));
+        backend.close(b);
+        assert_eq!(backend.rect(a), rect(0, 0, 1000, 600));
+    }
+
+    #[test]
+    fn minimized_and_other_desktop_windows_are_left_out() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        backend.switch_desktop(2);
+        let elsewhere = backend.open(3, "elsewhere", rect(10, 10, 200, 200));
+        backend.switch_desktop(0);
+        let mut wm = window_manager(&backend);
+        assert_eq!(backend.rect(elsewhere), rect(10, 10, 200, 200));
+        backend.update(b, |w| w.is_iconic = true);
+        assert!(send(&mut wm, MSG_MINIMIZESTART, 0, b));
+        assert_eq!(backend.rect(a), rect(0, 0, 1000, 600));
+    }
+
+    #[test]
+    fn dropping_a_window_on_another_takes_its_place() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        let c = backend.open(3, "c", rect(10, 10, 200, 200));
+        let mut wm = window_manager(&backend);
+        assert_eq!(backend.rect(c), rect(500, 300, 1000, 600));
+        backend.set_cursor_pos(Point { x: 700, y: 400 });
+        assert!(send(&mut wm, MSG_MOVESIZEEND, 0, a));
+        assert_eq!(backend.rect(b), rect(0, 0, 500, 600));
+        assert_eq!(backend.rect(c), rect(500, 0, 1000, 300));
+        assert_eq!(backend.rect(a), rect(500, 300, 1000, 600));
+    }
+
+    #[test]
+    fn window_refusing_to_shrink_is_given_room() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        backend.update(b, |w| w.min_size.width = 700);
+        window_manager(&backend);
+        assert_eq!(backend.rect(a), rect(0, 0, 300, 600));
+        assert_eq!(backend.rect(b), rect(300, 0, 1000, 600));
+    }
+
+    #[test]
+    fn learned_min_size_is_dropped_once_the_window_shrinks_again() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        backend.update(b, |w| w.min_size.width = 700);
+        let mut wm = window_manager(&backend);
+        assert_eq!(backend.rect(b), rect(300, 0, 1000, 600));
+        backend.update(b, |w| w.min_size.width = 0);
+        backend.set_cursor_pos(Point { x: 600, y: 300 });
+        assert!(send(&mut wm, MSG_MOVESIZEEND, 0, b));
+        assert_eq!(backend.rect(a), rect(0, 0, 500, 600));
+        assert_eq!(backend.rect(b), rect(500, 0, 1000, 600));
+    }
+
+    #[test]
+    fn monitors_are_tiled_separately() {
+        let backend = FakeBackend::new(vec![
+            monitor(rect(0, 0, 1000, 600)),
+            monitor(rect(1000, 0, 2000, 600)),
+        ]);
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(1010, 10, 1200, 200));
+        let c = backend.open(3, "c", rect(1010, 10, 1200, 200));
+        let mut wm = window_manager(&backend);
+        assert_eq!(backend.rect(a), rect(0, 0, 1000, 600));
+        assert_eq!(backend.rect(b), rect(1000, 0, 1500, 600));
+        assert_eq!(backend.rect(c), rect(1500, 0, 2000, 600));
+        assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWACTIVATED, c));
+        assert!(send(&mut wm, WM_COMMAND, CMD_SEND_TO_MONITOR_LEFT, HWND(0)));
+        assert_eq!(backend.rect(a), rect(0, 0, 500, 600));
+        assert_eq!(backend.rect(c), rect(500, 0, 1000, 600));
+        assert_eq!(backend.rect(b), rect(1000, 0, 2000, 600));
+    }
+
+    #[test]
+    fn tiling_trees_are_kept_per_monitor() {
+        let backend = FakeBackend::new(vec![
+            monitor(rect(0, 0, 1000, 600)),
+            monitor(rect(1000, 0, 2000, 600)),
+        ]);
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        let c = backend.open(3, "c", rect(1010, 10, 1200, 200));
+        let mut wm = window_manager(&backend);
+        for focused in [c, a] {
+            assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWACTIVATED, focused));
+            wm.handle_request(Request::SetLayout {
+                layout: BSP_LAYOUT.to_owned(),
+            });
+        }
+        let d = backend.open(4, "d", rect(1010, 10, 1200, 200));
+        assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWCREATED, d));
+        assert!(send(&mut wm, WM_COMMAND, CMD_ROTATE_SPLIT, HWND(0)));
+        assert_eq!(backend.rect(a), rect(0, 0, 1000, 300));
+        assert_eq!(backend.rect(b), rect(0, 300, 1000, 600));
+        assert_eq!(backend.rect(c), rect(1000, 0, 1500, 600));
+        assert_eq!(backend.rect(d), rect(1500, 0, 2000, 600));
+    }
+
+    #[test]
+    fn hidden_tiling_tree_is_left_alone() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        let mut wm = window_manager(&backend);
+        assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWACTIVATED, a));
+        send(&mut wm, WM_COMMAND, CMD_ROTATE_SPLIT, HWND(0));
+        send(&mut wm, WM_COMMAND, CMD_BALANCE_SPLITS, HWND(0));
+        wm.handle_request(Request::SetLayout {
+            layout: BSP_LAYOUT.to_owned(),
+        });
+        assert_eq!(backend.rect(a), rect(0, 0, 500, 600));
+        assert_eq!(backend.rect(b), rect(500, 0, 1000, 600));
+    }
+
+    #[test]
+    fn scrolled_off_columns_stay_on_their_monitor() {
+        let backend = FakeBackend::new(vec![
+            monitor(rect(0, 0, 1000, 600)),
+            monitor(rect(1000, 0, 2000, 600)),
+        ]);
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        let c = backend.open(3, "c", rect(10, 10, 200, 200));
+        let d = backend.open(4, "d", rect(1010, 10, 1200, 200));
+        let mut wm = window_manager(&backend);
+        assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWACTIVATED, a));
+        wm.handle_request(Request::SetLayout {
+            layout: SCROLLING_LAYOUT.to_owned(),
+        });
+        assert_eq!(backend.rect(c), rect(1000, 0, 1500, 600));
+        assert!(send(&mut wm, MSG_MINIMIZEEND, 0, b));
+        assert_eq!(backend.rect(b), rect(500, 0, 1000, 600));
+        assert_eq!(backend.rect(c), rect(1000, 0, 1500, 600));
+        assert_eq!(backend.rect(d), rect(1000, 0, 2000, 600));
+        assert_eq!(wm.state().monitors[1].windows.len(), 1);
+    }
+
+    #[test]
+    fn display_change_repartitions_windows() {
+        let backend = FakeBackend::new(vec![
+            monitor(rect(0, 0, 1000, 600)),
+            monitor(rect(1000, 0, 2000, 600)),
+        ]);
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(1010, 10, 1200, 200));
+        let mut wm = window_manager(&backend);
+        assert_eq!(backend.rect(b), rect(1000, 0, 2000, 600));
+        backend.set_monitors(vec![monitor(rect(0, 0, 1000, 600))]);
+        assert!(send(&mut wm, WM_DISPLAYCHANGE, 0, HWND(0)));
+        assert_eq!(backend.rect(a), rect(0, 0, 500, 600));
+        assert_eq!(backend.rect(b), rect(500, 0, 1000, 600));
+    }
+
+    #[test]
+    fn unknown_messages_are_not_handled() {
+        let backend = single_monitor();
+        let mut wm = window_manager(&backend);
+        assert!(!send(&mut wm, WM_USER + 0x100, 0, HWND(0)));
+    }
+
+    #[test]
+    fn floating_window_is_left_out_of_the_layout() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        let mut wm = window_manager(&backend);
+        assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWACTIVATED, a));
+        assert_eq!(wm.handle_request(Request::ToggleFloat), Response::Ok);
+        assert_eq!(backend.rect(a), rect(0, 0, 500, 600));
+        assert_eq!(backend.rect(b), rect(0, 0, 1000, 600));
+        let floating: Vec<bool> = wm.state().monitors[0]
+            .windows
+            .iter()
+            .map(|w| w.floating)
+            .collect();
+        assert_eq!(floating, [false, true]);
+        wm.handle_request(Request::ToggleFloat);
+        assert_eq!(backend.rect(b), rect(500, 0, 1000, 600));
+    }
+
+    #[test]
+    fn swap_trades_places_with_the_next_window() {
+        let backend = single_monitor();
+        let a = backend.open(1, "a", rect(10, 10, 200, 200));
+        let b = backend.open(2, "b", rect(10, 10, 200, 200));
+        let mut wm = window_manager(&backend);
+        assert!(send(&mut wm, SHELL_HOOK, HSHELL_WINDOWACTIVATED, a));
+        assert_eq!(wm.handle_request(Request::Swap), Response::Ok);
+        assert_eq!(backend.rect(a), rect(500, 0, 1000, 600));
+        assert_eq!(backend.rect(b), rect(0, 0, 500, 600));
+        assert_eq!(wm.handle_request(Request::FocusNext), Response::Ok);
+        assert_eq!(backend.foreground_window(), b);
+    }
+
+    #[test]
+    fn explanation_lists_every_reason_a_window_is_left_alone() {
+        let backend = single_monitor();
+        let app = backend.open(1, "app", rect(10, 10, 200, 200));
+        let tool = backend.open(2, "", rect(10, 10, 200, 200));
+        backend.update(tool, |w| w.info.exstyle = WS_EX_TOOLWINDOW.0);
+        let wm = window_manager(&backend);
+        let explanation = wm.explain(app);
+        assert!(explanation.managed);
+        assert_eq!(explanation.monitor, Some(0));
+        assert!(explanation.rejections.is_empty());
+        assert_eq!(
+            wm.explain(tool).rejections,
+            ["it has no title", "it is a tool window"]
+        );
+    }
+
+    #[test]
+    fn layouts_are_set_by_name_in_any_case() {
+        let backend = single_monitor();
+        let mut wm = window_manager(&backend);
+        let response = wm.handle_request(Request::SetLayout {
+            layout: "columns".to_owned(),
+        });
+        assert_eq!(response, Response::Ok);
+        assert_eq!(wm.state().monitors[0].layout, "Columns");
+    }
+
+    #[test]
+    fn unknown_layout_is_refused() {
+        let backend = single_monitor();
+        let mut wm = window_manager(&backend);
+        let response = wm.handle_request(Request::SetLayout {
+            layout: "Nonexistent".to_owned(),
+        });
+        assert!(matches!(response, Response::Error { .. }));
+    }
+}